@@ -0,0 +1,143 @@
+use std::io::{self, Write};
+
+use clap::Parser;
+use miden_client::{
+    accounts::AccountId,
+    crypto::FeltRng,
+    notes::{Note, NoteId},
+    Client,
+};
+use miden_order_book::{
+    clob_data::ClobData,
+    constants::{CLOB_DATA_FILE_PATH, ORDER_BOOK_DB_FILE_PATH},
+    note::create_reclaim_transaction_request,
+    order::Order,
+    store::OrderBookStore,
+    utils::{get_swapp_notes_by_creator, print_order_table, sync_state},
+};
+
+use crate::errors::CliError;
+
+// Cancel COMMAND
+// ================================================================================================
+
+#[derive(Debug, Clone, Parser)]
+#[clap(about = "Cancel orders by reclaiming their SWAPP notes in a single transaction")]
+pub struct CancelCmd {
+    /// Ids of the SWAPP notes of the orders
+    #[clap(required_unless_present_any = ["all", "market"])]
+    pub note_ids: Vec<String>,
+
+    /// Cancel every open order of the account
+    #[clap(long, conflicts_with = "note_ids")]
+    pub all: bool,
+
+    /// Only cancel the open orders of this market, BASE/QUOTE
+    #[clap(long, conflicts_with = "note_ids")]
+    pub market: Option<String>,
+
+    /// Account which created the orders, defaults to the user of the CLOB data
+    #[clap(long)]
+    pub user: Option<String>,
+}
+
+impl CancelCmd {
+    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), CliError> {
+        let clob_data = ClobData::load(CLOB_DATA_FILE_PATH)?;
+        let account_id = clob_data.user_or_default(self.user.as_deref())?;
+
+        // The SWAPP notes must be tracked by the client, see the query command
+        let mut store = OrderBookStore::open(ORDER_BOOK_DB_FILE_PATH)?;
+        sync_state(client, &mut store).await?;
+        let notes = if self.note_ids.is_empty() {
+            let tags = match &self.market {
+                Some(symbol) => {
+                    let market = clob_data.markets.get(symbol)?;
+                    vec![market.bid_tag(), market.ask_tag()]
+                }
+                None => Vec::new(),
+            };
+            get_swapp_notes_by_creator(client, &mut store, account_id, &tags).await?
+        } else {
+            self.get_notes(client).await?
+        };
+
+        if notes.is_empty() {
+            println!("No open order to cancel.");
+            return Ok(());
+        }
+
+        // Preview the assets given back to the account
+        let orders = notes
+            .iter()
+            .map(|note| Order::try_from(note).map_err(|e| CliError::InvalidNote(note.id(), e)))
+            .collect::<Result<Vec<Order>, CliError>>()?;
+        print_order_table("Orders to cancel:", &orders);
+        Self::print_reclaimed_assets(account_id, &orders);
+
+        print!("Do you want to proceed with the cancellation? [Y/n]: ");
+        io::stdout().flush().map_err(CliError::Prompt)?;
+
+        let mut input = String::new();
+        io::stdin()
+            .read_line(&mut input)
+            .map_err(CliError::Prompt)?;
+
+        let proceed = input.trim().to_lowercase();
+        if proceed != "y" && proceed != "yes" && !proceed.is_empty() {
+            println!("Cancellation aborted by user.");
+            return Ok(());
+        }
+
+        let transaction_request = create_reclaim_transaction_request(account_id, &notes)?;
+        let transaction = client
+            .new_transaction(account_id, transaction_request)
+            .await
+            .map_err(CliError::TransactionFailed)?;
+        client
+            .submit_transaction(transaction)
+            .await
+            .map_err(CliError::TransactionFailed)?;
+
+        let block_num = client.get_sync_height().await?;
+        store.record_cancels(&orders, block_num)?;
+
+        println!("{} orders cancelled.", orders.len());
+        Ok(())
+    }
+
+    async fn get_notes(&self, client: &Client<impl FeltRng>) -> Result<Vec<Note>, CliError> {
+        let mut notes = Vec::with_capacity(self.note_ids.len());
+        for note_id in self.note_ids.iter() {
+            let note_id = NoteId::try_from_hex(note_id).map_err(|e| {
+                CliError::InvalidArgument(format!("Invalid note id {}: {}", note_id, e))
+            })?;
+            let record = client
+                .get_input_note(note_id)
+                .await
+                .map_err(|e| format!("SWAPP note {} is not tracked: {}", note_id, e))?;
+            notes.push(Note::try_from(record).map_err(|e| e.to_string())?);
+        }
+
+        Ok(notes)
+    }
+
+    fn print_reclaimed_assets(account_id: AccountId, orders: &[Order]) {
+        let mut reclaimed: Vec<(AccountId, u64)> = Vec::new();
+        for order in orders {
+            let asset = order.source_asset().unwrap_fungible();
+            match reclaimed
+                .iter_mut()
+                .find(|(id, _)| *id == asset.faucet_id())
+            {
+                Some((_, amount)) => *amount += asset.amount(),
+                None => reclaimed.push((asset.faucet_id(), asset.amount())),
+            }
+        }
+
+        println!("Assets returned to {}:", account_id);
+        for (faucet_id, amount) in reclaimed {
+            println!("  {} of faucet {}", amount, faucet_id);
+        }
+    }
+}
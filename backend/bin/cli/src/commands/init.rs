@@ -1,19 +1,25 @@
 use clap::Parser;
 use colored::Colorize;
 use log::warn;
-use miden_order_book::constants::{ACCOUNTS_DIR, CLOB_DATA_FILE_PATH, DB_FILE_PATH};
+use miden_order_book::{
+    config::ClientConfig,
+    constants::{ACCOUNTS_DIR, CLOB_DATA_FILE_PATH, ORDER_BOOK_DB_FILE_PATH},
+};
 use std::{
     fs::{self},
     path::Path,
 };
 
+use crate::errors::CliError;
+
 #[derive(Debug, Clone, Parser)]
 #[clap(about = "Initialize the order book")]
 pub struct InitCmd {}
 
 impl InitCmd {
-    pub fn execute(&self) -> Result<(), String> {
-        self.remove_file_if_exists(DB_FILE_PATH)?;
+    pub fn execute(&self, config: &ClientConfig) -> Result<(), CliError> {
+        self.remove_file_if_exists(&config.store_path)?;
+        self.remove_file_if_exists(ORDER_BOOK_DB_FILE_PATH)?;
         self.remove_file_if_exists(CLOB_DATA_FILE_PATH)?;
         self.remove_folder_if_exists(ACCOUNTS_DIR)?;
         self.print_cool_start_message();
@@ -21,7 +27,7 @@ impl InitCmd {
         Ok(())
     }
 
-    pub fn remove_file_if_exists(&self, file_path: &str) -> Result<(), String> {
+    pub fn remove_file_if_exists(&self, file_path: &str) -> Result<(), CliError> {
         let path = Path::new(file_path);
         if path.exists() {
             fs::remove_file(path)
@@ -30,7 +36,7 @@ impl InitCmd {
         Ok(())
     }
 
-    fn remove_folder_if_exists(&self, folder_path: &str) -> Result<(), String> {
+    fn remove_folder_if_exists(&self, folder_path: &str) -> Result<(), CliError> {
         let path = Path::new(folder_path);
         if path.exists() && path.is_dir() {
             fs::remove_dir_all(path)
@@ -1,28 +1,48 @@
 use clap::Parser;
 use miden_client::{crypto::FeltRng, Client};
 use miden_order_book::{
-    order::{sort_orders, Order},
-    utils::{get_notes_by_tag, print_order_table},
+    clob_data::ClobData,
+    constants::{CLOB_DATA_FILE_PATH, ORDER_BOOK_DB_FILE_PATH},
+    order::Order,
+    store::OrderBookStore,
+    utils::{get_orders_by_tag, print_depth, print_order_table, print_rejected_notes},
 };
 
+use crate::errors::CliError;
+
 #[derive(Debug, Clone, Parser)]
 #[clap(about = "List avaible order book orders")]
 pub struct ListCmd {
-    // tags
-    pub tags: Vec<u32>,
+    /// Market to list, BASE/QUOTE, defaults to the first market of the CLOB data
+    #[clap(long)]
+    pub market: Option<String>,
+
+    /// Number of price levels to show per side
+    #[clap(long, default_value_t = 10)]
+    pub depth: usize,
 }
 
 impl ListCmd {
-    pub async fn execute(&self, client: &Client<impl FeltRng>) -> Result<(), String> {
-        for tag in self.tags.clone() {
-            let notes = get_notes_by_tag(client, tag.into()).await;
-            let orders: Vec<Order> = notes.into_iter().map(Order::from).collect();
+    pub async fn execute(&self, client: &Client<impl FeltRng>) -> Result<(), CliError> {
+        let clob_data = ClobData::load(CLOB_DATA_FILE_PATH)?;
+        let market = clob_data.markets.get_or_default(self.market.as_deref())?;
 
-            let sorted_orders = sort_orders(orders);
-            let title = format!("Relevant orders for tag {}:", tag);
-            print_order_table(title.as_str(), &sorted_orders);
+        let mut store = OrderBookStore::open(ORDER_BOOK_DB_FILE_PATH)?;
+        let mut book = market.book();
+        for tag in [book.ask_tag(), book.bid_tag()] {
+            let (orders, rejected) = get_orders_by_tag(client, &mut store, tag).await?;
+            print_rejected_notes(&rejected);
+            for order in orders {
+                let _ = book.insert(order);
+            }
         }
 
+        let asks: Vec<Order> = book.asks().copied().collect();
+        let bids: Vec<Order> = book.bids().copied().collect();
+        print_order_table("Asks:", &asks);
+        print_order_table("Bids:", &bids);
+        print_depth("Depth:", &book.depth(self.depth));
+
         Ok(())
     }
 }
@@ -0,0 +1,119 @@
+use clap::{Parser, Subcommand};
+use miden_client::accounts::AccountId;
+use miden_order_book::{clob_data::ClobData, constants::CLOB_DATA_FILE_PATH, market::Market};
+
+use crate::errors::CliError;
+
+// Market COMMAND
+// ================================================================================================
+
+#[derive(Debug, Clone, Parser)]
+#[clap(about = "Manage the markets of the order book")]
+pub struct MarketCmd {
+    #[clap(subcommand)]
+    action: MarketAction,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum MarketAction {
+    /// List the registered markets
+    List,
+    /// Register a market, replacing the market with the same symbol
+    Add {
+        /// Market symbol, BASE/QUOTE
+        market: String,
+
+        /// Base faucet id
+        base_faucet: String,
+
+        /// Quote faucet id
+        quote_faucet: String,
+
+        /// Decimals of the base asset
+        #[clap(long, default_value_t = 10)]
+        base_decimals: u8,
+
+        /// Decimals of the quote asset
+        #[clap(long, default_value_t = 10)]
+        quote_decimals: u8,
+
+        /// Smallest price increment, in smallest units of the quote asset per whole base asset
+        #[clap(long, default_value_t = 1)]
+        tick_size: u64,
+
+        /// Smallest quantity increment, in base asset
+        #[clap(long, default_value_t = 1)]
+        lot_size: u64,
+    },
+}
+
+impl MarketCmd {
+    pub fn execute(&self) -> Result<(), CliError> {
+        let mut clob_data = ClobData::load(CLOB_DATA_FILE_PATH)?;
+
+        match &self.action {
+            MarketAction::List => {
+                for market in clob_data.markets.markets() {
+                    Self::print_market(market);
+                }
+            }
+            MarketAction::Add {
+                market,
+                base_faucet,
+                quote_faucet,
+                base_decimals,
+                quote_decimals,
+                tick_size,
+                lot_size,
+            } => {
+                let (base_symbol, quote_symbol) = market.split_once('/').ok_or_else(|| {
+                    CliError::InvalidArgument(format!("Market {} should be BASE/QUOTE", market))
+                })?;
+                let base_faucet_id = Self::parse_faucet_id(base_faucet)?;
+                let quote_faucet_id = Self::parse_faucet_id(quote_faucet)?;
+
+                let market = Market::new(
+                    base_symbol,
+                    base_faucet_id,
+                    *base_decimals,
+                    quote_symbol,
+                    quote_faucet_id,
+                    *quote_decimals,
+                    *tick_size,
+                    *lot_size,
+                )?;
+                Self::print_market(&market);
+
+                clob_data.markets.add(market);
+                clob_data.save(CLOB_DATA_FILE_PATH)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_faucet_id(faucet_id: &str) -> Result<AccountId, CliError> {
+        AccountId::from_hex(faucet_id).map_err(|e| {
+            CliError::InvalidArgument(format!("Invalid faucet id {}: {}", faucet_id, e))
+        })
+    }
+
+    fn print_market(market: &Market) {
+        println!("{}", market.symbol());
+        println!("  base faucet: {}", market.base_faucet_id());
+        println!("  quote faucet: {}", market.quote_faucet_id());
+        println!(
+            "  decimals: {} / {}",
+            market.base_decimals(),
+            market.quote_decimals()
+        );
+        println!(
+            "  tick size: {} {}",
+            market.display_tick_size(),
+            market.symbol()
+        );
+        println!("  lot size: {}", market.lot_size());
+        println!("  ask tag: {}", market.ask_tag());
+        println!("  bid tag: {}", market.bid_tag());
+    }
+}
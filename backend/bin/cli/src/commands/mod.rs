@@ -1,5 +1,7 @@
+pub mod cancel;
 pub mod init;
 pub mod list;
+pub mod market;
 pub mod order;
 pub mod query;
 pub mod setup;
@@ -2,158 +2,338 @@ use std::io::{self, Write};
 
 use miden_client::{
     accounts::AccountId,
-    assets::{Asset, FungibleAsset},
     crypto::FeltRng,
-    notes::{build_swap_tag, NoteId, NoteType},
-    transactions::{NoteArgs, SwapTransactionData, TransactionRequest},
-    Client, Felt, ZERO,
+    notes::{Note, NoteType},
+    Client, ZERO,
 };
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 
-use crate::commands::sync::SyncCmd;
+use crate::{commands::sync::SyncCmd, errors::CliError};
 
 use miden_order_book::{
-    errors::OrderError,
-    note::create_expected_partial_swapp_note,
-    order::{match_orders, sort_orders, Order},
-    utils::{get_notes_by_tag, print_balance_update, print_order_table},
+    book::{OrderBook, Side},
+    clob_data::ClobData,
+    constants::{CLOB_DATA_FILE_PATH, ORDER_BOOK_DB_FILE_PATH},
+    errors::{OrderError, TransactionError},
+    market::Market,
+    note::{
+        create_expected_partial_swapp_note, create_fill_transaction_request, create_swapp_note,
+    },
+    order::{
+        execute_order, fill_market_order, prevent_self_trades, Execution, FillPlan, Order,
+        OrderValidity, SelfTradePrevention, SlippageGuard,
+    },
+    store::OrderBookStore,
+    utils::{get_orders_by_tag, print_balance_update, print_order_table, print_rejected_notes},
 };
 
-#[derive(Debug, Clone, Parser)]
-#[command(about = "Execute an order")]
-pub struct OrderCmd {
-    /// Account executing the order
-    pub user: String,
+/// Side of an order in its market.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OrderSide {
+    /// Offer the quote asset in exchange for the base asset
+    Buy,
+    /// Offer the base asset in exchange for the quote asset
+    Sell,
+}
 
-    /// Target faucet id
-    pub target_faucet: String,
+impl From<OrderSide> for Side {
+    fn from(side: OrderSide) -> Self {
+        match side {
+            OrderSide::Buy => Side::Bid,
+            OrderSide::Sell => Side::Ask,
+        }
+    }
+}
 
-    /// Target asset amount
-    pub target_amount: u64,
+/// Handling of resting orders of the account an order would match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum SelfTrade {
+    /// Skip them, the order is filled by the other orders
+    #[default]
+    Skip,
+    /// Cancel them, their SWAPP notes are reclaimed in the fill transaction
+    CancelResting,
+    /// Cancel the incoming order
+    CancelIncoming,
+}
 
-    /// Source faucet id
-    pub source_faucet: String,
+impl From<SelfTrade> for SelfTradePrevention {
+    fn from(self_trade: SelfTrade) -> Self {
+        match self_trade {
+            SelfTrade::Skip => SelfTradePrevention::Skip,
+            SelfTrade::CancelResting => SelfTradePrevention::CancelResting,
+            SelfTrade::CancelIncoming => SelfTradePrevention::CancelIncoming,
+        }
+    }
+}
 
-    /// Source asset amount
-    pub source_amount: u64,
+#[derive(Debug, Clone, Parser)]
+#[command(about = "Execute an order")]
+pub struct OrderCmd {
+    /// Account executing the order, defaults to the user of the CLOB data
+    #[clap(long)]
+    pub user: Option<String>,
+
+    /// Market of the order, BASE/QUOTE, defaults to the first market of the CLOB data
+    #[clap(long)]
+    pub market: Option<String>,
+
+    /// Side of the order
+    #[clap(value_enum)]
+    pub side: OrderSide,
+
+    /// Limit price, a decimal in quote asset per base asset such as 0.5, a multiple of the tick
+    /// size of the market. Without it the order is a market order, bounded by --worst-price or
+    /// --max-slippage-bps
+    #[clap(long, required_unless_present_any = ["worst_price", "max_slippage_bps"])]
+    pub price: Option<String>,
+
+    /// Quantity, in smallest units of the base asset, a multiple of the lot size of the market
+    #[clap(long)]
+    pub qty: u64,
+
+    /// Block before which the order can not be filled when it is added to the order book
+    #[clap(long, requires = "price")]
+    pub good_after_block: Option<u32>,
+
+    /// Block after which the order can not be filled when it is added to the order book, it can
+    /// still be cancelled
+    #[clap(long, requires = "price")]
+    pub expiry_block: Option<u32>,
+
+    /// Only fill the order if its full quantity is matched, never post it
+    #[clap(long, conflicts_with = "post_only", requires = "price")]
+    pub fill_or_kill: bool,
+
+    /// Never take liquidity, only post the order if it does not cross the book
+    #[clap(long, requires = "price")]
+    pub post_only: bool,
+
+    /// Worst average price of a market order, a decimal in quote asset per base asset: the
+    /// highest price paid for a buy, the lowest price received for a sell
+    #[clap(long, conflicts_with_all = ["price", "max_slippage_bps"])]
+    pub worst_price: Option<String>,
+
+    /// Maximum deviation of the average price of a market order from the best price of the book,
+    /// in basis points
+    #[clap(long, conflicts_with = "price")]
+    pub max_slippage_bps: Option<u64>,
+
+    /// What to do with resting orders of the account the order would match. Market orders always
+    /// skip them
+    #[clap(long, value_enum, default_value_t = SelfTrade::Skip)]
+    pub self_trade: SelfTrade,
 }
 
 impl OrderCmd {
-    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), String> {
+    fn execution(&self) -> Execution {
+        if self.fill_or_kill {
+            Execution::FillOrKill
+        } else if self.post_only {
+            Execution::PostOnly
+        } else {
+            Execution::Limit
+        }
+    }
+
+    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), CliError> {
         // Parse id's
-        let account_id = AccountId::from_hex(self.user.as_str()).unwrap();
-        let source_faucet_id = AccountId::from_hex(self.source_faucet.as_str()).unwrap();
-        let target_faucet_id = AccountId::from_hex(self.target_faucet.as_str()).unwrap();
+        let clob_data = ClobData::load(CLOB_DATA_FILE_PATH)?;
+        let account_id = clob_data.user_or_default(self.user.as_deref())?;
+        let market = clob_data.markets.get_or_default(self.market.as_deref())?;
 
-        // Check if user has balance
-        let (account, _) = client.get_account(account_id).await.unwrap();
-        if account.vault().get_balance(source_faucet_id).unwrap() < self.source_amount {
-            panic!("User does not have enough assets to execute this order.");
+        match &self.price {
+            Some(price) => {
+                self.execute_limit_order(price, market, account_id, client)
+                    .await
+            }
+            None => self.execute_market_order(market, account_id, client).await,
         }
+    }
 
+    async fn execute_limit_order(
+        &self,
+        price: &str,
+        market: &Market,
+        account_id: AccountId,
+        client: &mut Client<impl FeltRng>,
+    ) -> Result<(), CliError> {
         // Build order
-        let source_asset =
-            Asset::Fungible(FungibleAsset::new(source_faucet_id, self.source_amount).unwrap());
-        let target_asset =
-            Asset::Fungible(FungibleAsset::new(target_faucet_id, self.target_amount).unwrap());
-        let incoming_order = Order::new(None, source_asset, target_asset);
-
-        // Get relevant notes
-        let tag = build_swap_tag(NoteType::Public, &target_asset, &source_asset).unwrap();
-        let notes = get_notes_by_tag(client, tag).await;
-        let existing_orders: Vec<Order> = notes.into_iter().map(Order::from).collect();
-
-        // fill order
-        match Self::fill_order(incoming_order, existing_orders) {
-            Ok((orders, partial_fill_amount, args)) => {
-                Self::fill_success(orders, partial_fill_amount, args, account_id, client)
-                    .await
-                    .map_err(|e| format!("Failed in fill success: {}", e))?
+        let incoming_order = market
+            .limit_order(self.side.into(), price, self.qty)?
+            .with_validity(OrderValidity::new(self.good_after_block, self.expiry_block))
+            .with_creator(account_id);
+        let source_asset = incoming_order.source_asset().unwrap_fungible();
+        let target_asset = incoming_order.target_asset().unwrap_fungible();
+        let (source_symbol, target_symbol) = match self.side {
+            OrderSide::Buy => (market.quote_symbol(), market.base_symbol()),
+            OrderSide::Sell => (market.base_symbol(), market.quote_symbol()),
+        };
+        println!(
+            "Offering {} {} for {} {}, implied price {} {}",
+            source_asset.amount(),
+            source_symbol,
+            target_asset.amount(),
+            target_symbol,
+            market.format_price(market.price_of(&incoming_order)),
+            market.symbol(),
+        );
+
+        // Check if user has balance
+        Self::check_balance(
+            account_id,
+            source_asset.faucet_id(),
+            source_asset.amount(),
+            client,
+        )
+        .await?;
+
+        // fill order, skipping the orders which can not be filled at the synced block
+        let book = self.get_book(market, client).await?;
+        let block_num = client.get_sync_height().await?;
+        let cancelled =
+            prevent_self_trades(incoming_order, &book, block_num, self.self_trade.into())?;
+        match execute_order(incoming_order, &book, block_num, self.execution()) {
+            Ok((plan, remaining_source)) => {
+                // the unfilled part of the order is posted in the same transaction
+                let remainder = incoming_order.remainder(remaining_source);
+                Self::fill_success(plan, cancelled, remainder, account_id, client).await?
             }
             Err(err) => match err {
-                OrderError::FailedFill(order) => Self::fill_failure(order, account_id, client)
-                    .await
-                    .map_err(|e| format!("Failed in fill failure: {}", e))?,
-                _ => panic!("Unknown error."),
+                OrderError::FailedFill(order) => {
+                    Self::fill_failure(order, cancelled, account_id, client).await?
+                }
+                err => return Err(err.into()),
             },
         }
 
         Ok(())
     }
 
-    pub fn fill_order(
-        incoming_order: Order,
-        existing_orders: Vec<Order>,
-    ) -> Result<(Vec<Order>, u64, Vec<NoteArgs>), OrderError> {
-        // Sort existing orders
-        let sorted_orders = sort_orders(existing_orders);
-
-        // Keep only orders that match incoming order
-        let mut matching_orders = Vec::new();
-        for order in sorted_orders {
-            match match_orders(incoming_order, order) {
-                Ok(order) => matching_orders.push(order),
-                Err(_) => continue,
+    async fn execute_market_order(
+        &self,
+        market: &Market,
+        account_id: AccountId,
+        client: &mut Client<impl FeltRng>,
+    ) -> Result<(), CliError> {
+        // Build order
+        let side = self.side.into();
+        let guard = match (&self.worst_price, self.max_slippage_bps) {
+            (Some(worst_price), _) => {
+                println!(
+                    "Market order for {} {}, worst average price {} {}",
+                    self.qty,
+                    market.base_symbol(),
+                    worst_price,
+                    market.symbol(),
+                );
+                market.worst_price_guard(side, worst_price)?
             }
-        }
-
-        let mut remaining_source = incoming_order.source_asset().unwrap_fungible().amount();
+            (None, Some(bps)) => {
+                println!(
+                    "Market order for {} {}, at most {} basis points from the best price",
+                    self.qty,
+                    market.base_symbol(),
+                    bps,
+                );
+                SlippageGuard::MaxSlippageBps(bps)
+            }
+            (None, None) => {
+                return Err(CliError::InvalidArgument(
+                    "A market order needs a slippage bound".to_string(),
+                ))
+            }
+        };
+        let mut market_order = market.market_order(side, self.qty, guard)?;
+        market_order.account_id = Some(account_id);
 
-        let mut final_orders = Vec::new();
-        let mut args = Vec::new();
-        for order in matching_orders {
-            let order_amount = order.target_asset().unwrap_fungible().amount();
+        // fill order, skipping the orders which can not be filled at the synced block
+        let book = self.get_book(market, client).await?;
+        let block_num = client.get_sync_height().await?;
+        let plan = fill_market_order(market_order, &book, block_num)?;
 
-            if remaining_source == 0 {
-                break;
-            }
+        // Check if user has balance
+        Self::check_balance(
+            account_id,
+            market_order.source_faucet_id,
+            plan.spent(),
+            client,
+        )
+        .await?;
+
+        Self::fill_success(plan, Vec::new(), None, account_id, client).await
+    }
 
-            if order_amount <= remaining_source {
-                remaining_source = remaining_source.saturating_sub(order_amount);
-                args.push([Felt::new(order_amount), ZERO, ZERO, ZERO]);
-                final_orders.push(order)
-            } else {
-                args.push([Felt::new(remaining_source), ZERO, ZERO, ZERO]);
-                final_orders.push(order);
-                break;
-            }
+    /// Returns the book of the market with the notes resting on the opposite side of the order.
+    async fn get_book(
+        &self,
+        market: &Market,
+        client: &Client<impl FeltRng>,
+    ) -> Result<OrderBook, CliError> {
+        let mut store = OrderBookStore::open(ORDER_BOOK_DB_FILE_PATH)?;
+        let mut book = market.book();
+        let tag = match self.side {
+            OrderSide::Buy => book.ask_tag(),
+            OrderSide::Sell => book.bid_tag(),
+        };
+        let (orders, rejected) = get_orders_by_tag(client, &mut store, tag).await?;
+        print_rejected_notes(&rejected);
+        for order in orders {
+            let _ = book.insert(order);
         }
 
-        if final_orders.len() == 0 {
-            return Err(OrderError::FailedFill(incoming_order));
+        Ok(book)
+    }
+
+    async fn check_balance(
+        account_id: AccountId,
+        faucet_id: AccountId,
+        amount: u64,
+        client: &Client<impl FeltRng>,
+    ) -> Result<(), CliError> {
+        let (account, _) = client.get_account(account_id).await?;
+        let balance = account.vault().get_balance(faucet_id)?;
+        if balance < amount {
+            return Err(CliError::InsufficientBalance(balance, amount));
         }
 
-        Ok((final_orders, remaining_source, args))
+        Ok(())
     }
 
     async fn fill_success(
-        orders: Vec<Order>,
-        partial_fill_amount: u64,
-        args: Vec<NoteArgs>,
+        plan: FillPlan,
+        cancelled: Vec<Order>,
+        remainder: Option<Order>,
         account_id: AccountId,
         client: &mut Client<impl FeltRng>,
-    ) -> Result<(), OrderError> {
+    ) -> Result<(), CliError> {
         // sync
         let sync = SyncCmd {};
-        sync.execute(client).await.unwrap();
+        sync.execute(client).await?;
 
         // print final orders
-        print_order_table("Final orders:", &orders);
+        print_order_table("Final orders:", &plan.orders());
 
         // print user balance update
-        print_balance_update(&orders, &args);
+        print_balance_update(&plan);
+
+        if !cancelled.is_empty() {
+            print_order_table("Own orders to cancel:", &cancelled);
+        }
+
+        if let Some(remainder) = remainder {
+            print_order_table("Unfilled remainder to post:", &[remainder]);
+        }
 
         // Prompt user for confirmation
         print!("Do you want to proceed with the execution? [Y/n]: ");
-        io::stdout()
-            .flush()
-            .map_err(|e| OrderError::InternalError(format!("Failed to flush stdout: {}", e)))?;
+        io::stdout().flush().map_err(CliError::Prompt)?;
 
         let mut input = String::new();
         io::stdin()
             .read_line(&mut input)
-            .map_err(|e| OrderError::InternalError(format!("Failed to read user input: {}", e)))?;
+            .map_err(CliError::Prompt)?;
 
         let proceed = input.trim().to_lowercase();
         if proceed != "y" && proceed != "yes" && !proceed.is_empty() {
@@ -161,84 +341,105 @@ impl OrderCmd {
             return Ok(());
         }
 
-        // Proceed with execution
-        let final_order_ids_and_args = orders
-            .clone()
-            .into_iter()
-            .enumerate()
-            .map(|(i, order)| {
-                order
-                    .id()
-                    .ok_or(OrderError::MissingId)
-                    .map(|id| (id, Some(args[i])))
-            })
-            .collect::<Result<Vec<(NoteId, Option<NoteArgs>)>, OrderError>>()?;
         // Create transaction
-
-        let expected_partial_swapp = if partial_fill_amount > 0 {
-            let not_fully_consumed_order = orders.last().unwrap();
-
-            let not_fully_consumed_note = client
-                .get_input_note(not_fully_consumed_order.id().unwrap())
-                .await
-                .unwrap();
-            Some(
-                create_expected_partial_swapp_note(
+        let partial_fill = plan
+            .partial_fill()
+            .and_then(|fill| fill.successor.map(|successor| (fill.paid, successor)));
+        let expected_partial_swapp = match partial_fill {
+            Some((fill_amount, successor)) => {
+                let not_fully_consumed_note = client.get_input_note(successor.parent_id).await?;
+                let not_fully_consumed_note =
+                    Note::try_from(not_fully_consumed_note).map_err(|e| e.to_string())?;
+                Some(create_expected_partial_swapp_note(
                     account_id,
-                    not_fully_consumed_note.try_into().unwrap(),
-                    partial_fill_amount,
-                    not_fully_consumed_order.price(),
-                )
-                .unwrap(),
-            )
-        } else {
-            None
+                    not_fully_consumed_note,
+                    fill_amount,
+                )?)
+            }
+            None => None,
         };
 
-        let mut transaction_request =
-            TransactionRequest::new().with_authenticated_input_notes(final_order_ids_and_args);
-
-        if let Some(swapp_note) = expected_partial_swapp {
-            transaction_request = transaction_request
-                .extend_advice_map(vec![(
-                    swapp_note.recipient().digest(),
-                    swapp_note.recipient().to_elements(),
-                )])
-                .with_expected_output_notes(vec![swapp_note]);
-        }
+        let remainder_swapp = remainder
+            .map(|remainder| {
+                create_swapp_note(
+                    account_id,
+                    remainder.source_asset(),
+                    remainder.target_asset(),
+                    NoteType::Public,
+                    ZERO,
+                    remainder.validity(),
+                    client.rng(),
+                )
+            })
+            .transpose()
+            .map_err(TransactionError::from)?;
+
+        let successor = expected_partial_swapp
+            .as_ref()
+            .map(|note| Order::try_from(note).map_err(|e| CliError::InvalidNote(note.id(), e)))
+            .transpose()?;
+        let posted = remainder_swapp
+            .as_ref()
+            .map(|note| Order::try_from(note).map_err(|e| CliError::InvalidNote(note.id(), e)))
+            .transpose()?;
+        let transaction_request = create_fill_transaction_request(
+            &plan,
+            &cancelled,
+            expected_partial_swapp,
+            remainder_swapp,
+        )?;
 
         let transaction = client
             .new_transaction(account_id, transaction_request)
             .await
-            .map_err(|e| {
-                OrderError::InternalError(format!("Failed to create transaction: {}", e))
-            })?;
+            .map_err(CliError::TransactionFailed)?;
 
-        client.submit_transaction(transaction).await.map_err(|e| {
-            OrderError::InternalError(format!("Failed to submit transaction: {}", e))
+        client
+            .submit_transaction(transaction)
+            .await
+            .map_err(CliError::TransactionFailed)?;
+
+        // Record fills in the order book store
+        let block_num = client.get_sync_height().await?;
+        OrderBookStore::open(ORDER_BOOK_DB_FILE_PATH).and_then(|mut store| {
+            store.record_fills(&plan, successor, block_num)?;
+            store.record_cancels(&cancelled, block_num)?;
+            match posted {
+                Some(order) => store.insert_order(order, None, block_num),
+                None => Ok(()),
+            }
         })?;
 
-        println!("Order filled successfully.");
+        match posted.and_then(|order| order.id()) {
+            Some(note_id) => println!(
+                "Order partially filled, the remainder is posted as SWAPP note {}.",
+                note_id
+            ),
+            None => println!("Order filled successfully."),
+        }
         Ok(())
     }
 
     async fn fill_failure(
         order: Order,
+        cancelled: Vec<Order>,
         account_id: AccountId,
         client: &mut Client<impl FeltRng>,
-    ) -> Result<(), OrderError> {
+    ) -> Result<(), CliError> {
         println!("Unable to fill the requested order.");
 
+        if !cancelled.is_empty() {
+            print_order_table("Own orders to cancel:", &cancelled);
+        }
+
         // Prompt user for confirmation
         print!("Do you want to add order to the order book? [Y/n]: ");
-        io::stdout()
-            .flush()
-            .map_err(|e| OrderError::InternalError(format!("Failed to flush stdout: {}", e)))?;
+        io::stdout().flush().map_err(CliError::Prompt)?;
 
         let mut input = String::new();
         io::stdin()
             .read_line(&mut input)
-            .map_err(|e| OrderError::InternalError(format!("Failed to read user input: {}", e)))?;
+            .map_err(CliError::Prompt)?;
 
         let proceed = input.trim().to_lowercase();
         if proceed != "y" && proceed != "yes" && !proceed.is_empty() {
@@ -246,23 +447,40 @@ impl OrderCmd {
             return Ok(());
         }
 
-        let swap_data =
-            SwapTransactionData::new(account_id, order.source_asset(), order.target_asset());
+        let note = create_swapp_note(
+            account_id,
+            order.source_asset(),
+            order.target_asset(),
+            NoteType::Public,
+            ZERO,
+            order.validity(),
+            client.rng(),
+        )
+        .map_err(TransactionError::from)?;
+        let note_id = note.id();
+        let posted = Order::try_from(&note).map_err(|e| CliError::InvalidNote(note_id, e))?;
+        // own orders the posted order would match are reclaimed in the same transaction
         let transaction_request =
-            TransactionRequest::swap(swap_data, NoteType::Public, client.rng()).unwrap();
+            create_fill_transaction_request(&FillPlan::default(), &cancelled, None, Some(note))?;
 
         let transaction = client
             .new_transaction(account_id, transaction_request)
             .await
-            .map_err(|e| {
-                OrderError::InternalError(format!("Failed to create transaction: {}", e))
-            })?;
+            .map_err(CliError::TransactionFailed)?;
+
+        client
+            .submit_transaction(transaction)
+            .await
+            .map_err(CliError::TransactionFailed)?;
 
-        client.submit_transaction(transaction).await.map_err(|e| {
-            OrderError::InternalError(format!("Failed to submit transaction: {}", e))
+        // Record the posted order and the cancels in the order book store
+        let block_num = client.get_sync_height().await?;
+        OrderBookStore::open(ORDER_BOOK_DB_FILE_PATH).and_then(|mut store| {
+            store.record_cancels(&cancelled, block_num)?;
+            store.insert_order(posted, None, block_num)
         })?;
 
-        println!("Failed to fill order: {:?}", order);
+        println!("Order posted as SWAPP note {}.", note_id);
 
         Ok(())
     }
@@ -1,24 +1,38 @@
 use clap::Parser;
 
-use miden_client::{crypto::FeltRng, Client};
+use miden_client::{crypto::FeltRng, notes::NoteTag, Client};
+use miden_order_book::{clob_data::ClobData, constants::CLOB_DATA_FILE_PATH};
 
 use super::sync::SyncCmd;
+use crate::errors::CliError;
 
 #[derive(Debug, Clone, Parser)]
 #[clap(about = "Query rollup for notes with a certain tag")]
 pub struct QueryCmd {
-    /// Tags to be queried from the rollup
-    #[clap(required = true)]
+    /// Tags to be queried from the rollup, defaults to the tags of the CLOB data
     pub tags: Vec<u32>,
+
+    /// Market whose bid and ask tags are queried, BASE/QUOTE
+    #[clap(long)]
+    pub market: Option<String>,
 }
 
 impl QueryCmd {
-    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), String> {
-        for tag in self.tags.clone() {
-            client
-                .add_note_tag(tag.into())
-                .await
-                .map_err(|e| e.to_string())?;
+    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), CliError> {
+        let clob_data = ClobData::load(CLOB_DATA_FILE_PATH)?;
+        let tags = match &self.market {
+            Some(symbol) => {
+                let market = clob_data.markets.get(symbol)?;
+                let mut tags: Vec<NoteTag> =
+                    self.tags.iter().map(|tag| NoteTag::from(*tag)).collect();
+                tags.extend([market.bid_tag(), market.ask_tag()]);
+                tags
+            }
+            None => clob_data.tags_or_default(&self.tags)?,
+        };
+
+        for tag in tags {
+            client.add_note_tag(tag).await?;
         }
 
         // Sync rollup state
@@ -1,4 +1,3 @@
-use core::panic;
 use std::{thread::sleep, time::Duration};
 
 use clap::Parser;
@@ -6,16 +5,25 @@ use miden_client::{
     accounts::{Account, AccountId, AccountStorageMode, AccountTemplate},
     assets::{Asset, FungibleAsset, TokenSymbol},
     crypto::FeltRng,
-    notes::{build_swap_tag, NoteTag, NoteType},
+    notes::{build_swap_tag, NoteId, NoteType},
     transactions::TransactionRequest,
     Client, Word,
 };
 
 use miden_order_book::{
-    constants::DB_FILE_PATH, note::create_partial_swap_notes_transaction_request,
-    utils::clear_notes_tables,
+    clob_data::{ClobData, FaucetData},
+    config::ClientConfig,
+    constants::CLOB_DATA_FILE_PATH,
+    errors::TransactionError,
+    market::Market,
+    note::create_partial_swap_notes_transaction_request,
 };
 
+use crate::errors::CliError;
+
+/// Decimals of the faucets created by the setup.
+const FAUCET_DECIMALS: u8 = 10;
+
 // Setup COMMAND
 // ================================================================================================
 
@@ -24,21 +32,25 @@ use miden_order_book::{
 pub struct SetupCmd {}
 
 impl SetupCmd {
-    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), String> {
+    pub async fn execute(
+        &self,
+        client: &mut Client<impl FeltRng>,
+        config: &ClientConfig,
+    ) -> Result<(), CliError> {
         // Sync rollup state
-        client.sync_state().await.unwrap();
+        client.sync_state().await?;
 
         // Create faucet accounts
-        let (faucet1, _) = Self::create_faucet(1000, "ASSETA", client).await;
-        let (faucet2, _) = Self::create_faucet(1000, "ASSETB", client).await;
+        let (faucet1, _) = Self::create_faucet(1000, "ASSETA", client).await?;
+        let (faucet2, _) = Self::create_faucet(1000, "ASSETB", client).await?;
 
         // Create user account
-        let (admin, _) = Self::create_wallet(client).await;
-        let (user, _) = Self::create_wallet(client).await;
+        let (admin, _) = Self::create_wallet(client).await?;
+        let (user, _) = Self::create_wallet(client).await?;
 
         // Mint assets for user
-        Self::fund_wallet(faucet1.id(), 500, faucet2.id(), 500, admin.id(), client).await;
-        Self::fund_wallet(faucet1.id(), 500, faucet2.id(), 500, user.id(), client).await;
+        Self::fund_wallet(faucet1.id(), 500, faucet2.id(), 500, admin.id(), client).await?;
+        Self::fund_wallet(faucet1.id(), 500, faucet2.id(), 500, user.id(), client).await?;
 
         // Create 50 ASSETA/ASSETB swap notes
         Self::create_partial_swap_notes(
@@ -50,7 +62,7 @@ impl SetupCmd {
             admin.id(),
             client,
         )
-        .await;
+        .await?;
 
         // Create 50 ASSETB/ASSETA swap notes
         Self::create_partial_swap_notes(
@@ -62,29 +74,56 @@ impl SetupCmd {
             admin.id(),
             client,
         )
-        .await;
+        .await?;
 
         // Build note tags
-        let asset_1 = Asset::Fungible(FungibleAsset::new(faucet1.id(), 0).unwrap());
-        let asset_2 = Asset::Fungible(FungibleAsset::new(faucet2.id(), 0).unwrap());
-        let swap_1_2_tag = build_swap_tag(NoteType::Public, &asset_1, &asset_2).unwrap();
-        let swap_2_1_tag = build_swap_tag(NoteType::Public, &asset_2, &asset_1).unwrap();
+        let asset_1 = Asset::Fungible(FungibleAsset::new(faucet1.id(), 0)?);
+        let asset_2 = Asset::Fungible(FungibleAsset::new(faucet2.id(), 0)?);
+        let swap_1_2_tag =
+            build_swap_tag(NoteType::Public, &asset_1, &asset_2).map_err(TransactionError::from)?;
+        let swap_2_1_tag =
+            build_swap_tag(NoteType::Public, &asset_2, &asset_1).map_err(TransactionError::from)?;
 
         if swap_1_2_tag == swap_2_1_tag {
-            panic!("Both asset tags should not be similar.");
+            return Err(CliError::Other(
+                "Both asset tags should not be similar.".to_string(),
+            ));
         }
 
-        // Sanitize client db
-        clear_notes_tables(DB_FILE_PATH);
-
-        Self::print_clob_data(
+        // Register the ASSETA/ASSETB market
+        let market = Market::new(
+            "ASSETA",
             faucet1.id(),
+            FAUCET_DECIMALS,
+            "ASSETB",
             faucet2.id(),
-            admin.id(),
-            user.id(),
-            swap_1_2_tag,
-            swap_2_1_tag,
-        );
+            FAUCET_DECIMALS,
+            1,
+            1,
+        )?;
+
+        // Persist the CLOB data, keeping the markets registered before
+        let mut clob_data = ClobData::load(CLOB_DATA_FILE_PATH)?;
+        clob_data.endpoint = Some(config.endpoint.clone());
+        clob_data.admin = Some(admin.id());
+        clob_data.user = Some(user.id());
+        clob_data.tags = vec![swap_1_2_tag, swap_2_1_tag];
+        clob_data.faucets = vec![
+            FaucetData {
+                symbol: "ASSETA".to_string(),
+                id: faucet1.id(),
+                decimals: FAUCET_DECIMALS,
+            },
+            FaucetData {
+                symbol: "ASSETB".to_string(),
+                id: faucet2.id(),
+                decimals: FAUCET_DECIMALS,
+            },
+        ];
+        clob_data.markets.add(market);
+        clob_data.save(CLOB_DATA_FILE_PATH)?;
+
+        Self::print_clob_data(&clob_data);
 
         println!("CLOB successfully setup.");
 
@@ -99,7 +138,7 @@ impl SetupCmd {
         total_asset_requesting: u64,
         user: AccountId,
         client: &mut Client<impl FeltRng>,
-    ) {
+    ) -> Result<(), CliError> {
         let transaction_request = create_partial_swap_notes_transaction_request(
             num_notes,
             user,
@@ -108,13 +147,10 @@ impl SetupCmd {
             faucet2,
             total_asset_requesting,
             client.rng(),
-        )
-        .unwrap();
-        let tx_result = client
-            .new_transaction(user, transaction_request)
-            .await
-            .unwrap();
-        client.submit_transaction(tx_result).await.unwrap();
+        )?;
+        Self::submit_transaction(user, transaction_request, client).await?;
+
+        Ok(())
     }
 
     async fn fund_wallet(
@@ -124,79 +160,96 @@ impl SetupCmd {
         asset_b_amount: u64,
         user: AccountId,
         client: &mut Client<impl FeltRng>,
-    ) {
+    ) -> Result<(), CliError> {
         // Setup mint
         let note_type = NoteType::Public;
 
         // Mint AssetA
-        let asset_a = FungibleAsset::new(faucet1, asset_a_amount).unwrap();
+        let asset_a = FungibleAsset::new(faucet1, asset_a_amount)?;
         let transaction_request =
             TransactionRequest::mint_fungible_asset(asset_a, user, note_type, client.rng())
-                .unwrap();
-        let tx_result = client
-            .new_transaction(faucet1, transaction_request)
-            .await
-            .unwrap();
-        let asset_a_note_id = tx_result.relevant_notes()[0].id();
-        client.submit_transaction(tx_result).await.unwrap();
+                .map_err(TransactionError::from)?;
+        let mut note_ids = Self::submit_transaction(faucet1, transaction_request, client).await?;
 
         // Mint AssetB
-        let asset_b = FungibleAsset::new(faucet2, asset_b_amount).unwrap();
+        let asset_b = FungibleAsset::new(faucet2, asset_b_amount)?;
         let transaction_request =
             TransactionRequest::mint_fungible_asset(asset_b, user, note_type, client.rng())
-                .unwrap();
-        let tx_result = client
-            .new_transaction(faucet2, transaction_request)
-            .await
-            .unwrap();
-        let asset_b_note_id = tx_result.relevant_notes()[0].id();
-        client.submit_transaction(tx_result).await.unwrap();
+                .map_err(TransactionError::from)?;
+        note_ids.extend(Self::submit_transaction(faucet2, transaction_request, client).await?);
 
         // Sync rollup state
         sleep(Duration::from_secs(20));
-        client.sync_state().await.unwrap();
+        client.sync_state().await?;
 
         // Fund receiving wallet
-        let tx_request = TransactionRequest::consume_notes(vec![asset_a_note_id, asset_b_note_id]);
-        let tx_result = client.new_transaction(user, tx_request).await.unwrap();
-        client.submit_transaction(tx_result).await.unwrap();
+        let tx_request = TransactionRequest::consume_notes(note_ids);
+        Self::submit_transaction(user, tx_request, client).await?;
+
+        Ok(())
+    }
+
+    /// Executes and submits a transaction, returning the ids of the notes it created.
+    async fn submit_transaction(
+        account_id: AccountId,
+        transaction_request: TransactionRequest,
+        client: &mut Client<impl FeltRng>,
+    ) -> Result<Vec<NoteId>, CliError> {
+        let tx_result = client
+            .new_transaction(account_id, transaction_request)
+            .await
+            .map_err(CliError::TransactionFailed)?;
+        let note_ids = tx_result
+            .relevant_notes()
+            .iter()
+            .map(|note| note.id())
+            .collect();
+        client
+            .submit_transaction(tx_result)
+            .await
+            .map_err(CliError::TransactionFailed)?;
+
+        Ok(note_ids)
     }
 
-    async fn create_wallet(client: &mut Client<impl FeltRng>) -> (Account, Word) {
+    async fn create_wallet(client: &mut Client<impl FeltRng>) -> Result<(Account, Word), CliError> {
         let wallet_template = AccountTemplate::BasicWallet {
             mutable_code: false,
             storage_mode: AccountStorageMode::Public,
         };
-        client.new_account(wallet_template).await.unwrap()
+        Ok(client.new_account(wallet_template).await?)
     }
 
     async fn create_faucet(
         max_supply: u64,
         token_symbol: &str,
         client: &mut Client<impl FeltRng>,
-    ) -> (Account, Word) {
+    ) -> Result<(Account, Word), CliError> {
+        let token_symbol = TokenSymbol::new(token_symbol).map_err(|e| {
+            CliError::InvalidArgument(format!("Invalid token symbol {}: {}", token_symbol, e))
+        })?;
         let faucet_template = AccountTemplate::FungibleFaucet {
-            token_symbol: TokenSymbol::new(token_symbol).unwrap(),
-            decimals: 10,
+            token_symbol,
+            decimals: FAUCET_DECIMALS,
             max_supply,
             storage_mode: AccountStorageMode::Public,
         };
-        client.new_account(faucet_template).await.unwrap()
+        Ok(client.new_account(faucet_template).await?)
     }
 
-    fn print_clob_data(
-        faucet1: AccountId,
-        faucet2: AccountId,
-        admin: AccountId,
-        user: AccountId,
-        swap_1_2_tag: NoteTag,
-        swap_2_1_tag: NoteTag,
-    ) {
-        println!("faucet1: {}", faucet1);
-        println!("faucet2: {}", faucet2);
-        println!("swap_1_2_tag: {}", swap_1_2_tag);
-        println!("swap_2_1_tag: {}", swap_2_1_tag);
-        println!("Admin: {}", admin);
-        println!("User: {}", user);
+    fn print_clob_data(clob_data: &ClobData) {
+        for faucet in clob_data.faucets.iter() {
+            println!("{}: {}", faucet.symbol, faucet.id);
+        }
+        for tag in clob_data.tags.iter() {
+            println!("Tag: {}", tag);
+        }
+        if let Some(admin) = clob_data.admin {
+            println!("Admin: {}", admin);
+        }
+        if let Some(user) = clob_data.user {
+            println!("User: {}", user);
+        }
+        println!("CLOB data written to {}", CLOB_DATA_FILE_PATH);
     }
 }
@@ -1,14 +1,20 @@
 use clap::Parser;
 
 use miden_client::{crypto::FeltRng, Client};
+use miden_order_book::{
+    constants::ORDER_BOOK_DB_FILE_PATH, store::OrderBookStore, utils::sync_state,
+};
+
+use crate::errors::CliError;
 
 #[derive(Debug, Clone, Parser)]
 #[clap(about = "Sync rollup state")]
 pub struct SyncCmd {}
 
 impl SyncCmd {
-    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), String> {
-        client.sync_state().await?;
+    pub async fn execute(&self, client: &mut Client<impl FeltRng>) -> Result<(), CliError> {
+        let mut store = OrderBookStore::open(ORDER_BOOK_DB_FILE_PATH)?;
+        sync_state(client, &mut store).await?;
         println!("Sync successful.");
         Ok(())
     }
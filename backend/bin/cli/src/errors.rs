@@ -0,0 +1,154 @@
+use std::{error::Error, fmt, io, process::ExitCode};
+
+use miden_client::{notes::NoteId, ClientError};
+use miden_objects::AssetError;
+use miden_order_book::errors::{
+    ClobDataError, ConfigError, MarketError, NoteIndexError, OrderError, StoreError,
+    SwappNoteError, TransactionError,
+};
+
+/// Errors of the CLI commands.
+///
+/// Errors of the library are shown as they are, the other variants add the context of the
+/// command which failed.
+#[derive(Debug)]
+pub enum CliError {
+    Config(ConfigError),
+    ClobData(ClobDataError),
+    Market(MarketError),
+    Order(OrderError),
+    Transaction(TransactionError),
+    Store(StoreError),
+    NoteIndex(NoteIndexError),
+    Client(ClientError),
+    InvalidNote(NoteId, SwappNoteError),
+    InvalidArgument(String),
+    InsufficientBalance(u64, u64),
+    TransactionFailed(ClientError),
+    Prompt(io::Error),
+    Other(String),
+}
+
+impl CliError {
+    /// Returns the exit code of the CLI: 2 when the command or its configuration is invalid,
+    /// 3 when the order is rejected and 1 when the command fails while running.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            CliError::Config(_)
+            | CliError::ClobData(_)
+            | CliError::Market(_)
+            | CliError::InvalidArgument(_) => ExitCode::from(2),
+            CliError::Order(_) | CliError::InvalidNote(..) | CliError::InsufficientBalance(..) => {
+                ExitCode::from(3)
+            }
+            _ => ExitCode::FAILURE,
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Config(err) => write!(f, "{}", err),
+            CliError::ClobData(err) => write!(f, "{}", err),
+            CliError::Market(err) => write!(f, "{}", err),
+            CliError::Order(err) => write!(f, "{}", err),
+            CliError::Transaction(err) => write!(f, "{}", err),
+            CliError::Store(err) => write!(f, "{}", err),
+            CliError::NoteIndex(err) => write!(f, "{}", err),
+            CliError::Client(err) => write!(f, "{}", err),
+            CliError::InvalidNote(note_id, _) => {
+                write!(f, "Note {} is not a valid SWAPP note", note_id)
+            }
+            CliError::InvalidArgument(msg) => write!(f, "{}", msg),
+            CliError::InsufficientBalance(balance, amount) => write!(
+                f,
+                "User does not have enough assets to execute this order, {} needed but {} held",
+                amount, balance
+            ),
+            CliError::TransactionFailed(_) => write!(f, "Failed to execute transaction"),
+            CliError::Prompt(_) => write!(f, "Failed to prompt for confirmation"),
+            CliError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for CliError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            // library errors are displayed as they are, so their sources are shown next
+            CliError::Config(err) => err.source(),
+            CliError::ClobData(err) => err.source(),
+            CliError::Market(err) => err.source(),
+            CliError::Order(err) => err.source(),
+            CliError::Transaction(err) => err.source(),
+            CliError::Store(err) => err.source(),
+            CliError::NoteIndex(err) => err.source(),
+            CliError::Client(err) => err.source(),
+            CliError::InvalidNote(_, err) => Some(err),
+            CliError::TransactionFailed(err) => Some(err),
+            CliError::Prompt(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for CliError {
+    fn from(err: ConfigError) -> Self {
+        CliError::Config(err)
+    }
+}
+
+impl From<ClobDataError> for CliError {
+    fn from(err: ClobDataError) -> Self {
+        CliError::ClobData(err)
+    }
+}
+
+impl From<MarketError> for CliError {
+    fn from(err: MarketError) -> Self {
+        CliError::Market(err)
+    }
+}
+
+impl From<OrderError> for CliError {
+    fn from(err: OrderError) -> Self {
+        CliError::Order(err)
+    }
+}
+
+impl From<TransactionError> for CliError {
+    fn from(err: TransactionError) -> Self {
+        CliError::Transaction(err)
+    }
+}
+
+impl From<AssetError> for CliError {
+    fn from(err: AssetError) -> Self {
+        CliError::Transaction(TransactionError::InvalidAsset(err))
+    }
+}
+
+impl From<StoreError> for CliError {
+    fn from(err: StoreError) -> Self {
+        CliError::Store(err)
+    }
+}
+
+impl From<NoteIndexError> for CliError {
+    fn from(err: NoteIndexError) -> Self {
+        CliError::NoteIndex(err)
+    }
+}
+
+impl From<ClientError> for CliError {
+    fn from(err: ClientError) -> Self {
+        CliError::Client(err)
+    }
+}
+
+impl From<String> for CliError {
+    fn from(msg: String) -> Self {
+        CliError::Other(msg)
+    }
+}
@@ -1,10 +1,19 @@
 mod commands;
+mod errors;
 
-use clap::Parser;
+use clap::{Args, Parser};
 use commands::{
-    init::InitCmd, list::ListCmd, order::OrderCmd, query::QueryCmd, setup::SetupCmd, sync::SyncCmd,
+    cancel::CancelCmd, init::InitCmd, list::ListCmd, market::MarketCmd, order::OrderCmd,
+    query::QueryCmd, setup::SetupCmd, sync::SyncCmd,
 };
-use miden_order_book::utils::setup_client;
+use miden_order_book::{
+    config::{ClientConfig, ClientOverrides, Network},
+    constants::CLIENT_CONFIG_FILE_PATH,
+    errors::ConfigError,
+    utils::setup_client,
+};
+
+pub use errors::CliError;
 
 /// Commands
 #[derive(Debug, Parser)]
@@ -12,7 +21,9 @@ pub enum Command {
     Init(InitCmd),
     Setup(SetupCmd),
     Order(OrderCmd),
+    Cancel(CancelCmd),
     List(ListCmd),
+    Market(MarketCmd),
     Sync(SyncCmd),
     Query(QueryCmd),
 }
@@ -28,21 +39,80 @@ pub enum Command {
 pub struct Cli {
     #[clap(subcommand)]
     action: Command,
+
+    #[clap(flatten)]
+    client: ClientArgs,
+}
+
+/// Client flags, each of them can also be set through the environment.
+#[derive(Debug, Clone, Args)]
+pub struct ClientArgs {
+    /// Client config file
+    #[clap(
+        long = "client-config",
+        env = "MIDEN_CLIENT_CONFIG",
+        default_value = CLIENT_CONFIG_FILE_PATH,
+        global = true
+    )]
+    pub client_config: String,
+
+    /// Network to connect to, localhost, devnet or testnet, sets the default endpoint and store
+    #[clap(long, env = "MIDEN_NETWORK", global = true)]
+    pub network: Option<Network>,
+
+    /// Node RPC endpoint, protocol://host:port
+    #[clap(long, env = "MIDEN_RPC_ENDPOINT", global = true)]
+    pub rpc_endpoint: Option<String>,
+
+    /// Node RPC timeout in milliseconds
+    #[clap(long, env = "MIDEN_RPC_TIMEOUT_MS", global = true)]
+    pub rpc_timeout_ms: Option<u64>,
+
+    /// Path of the client store
+    #[clap(long, env = "MIDEN_STORE_PATH", global = true)]
+    pub store_path: Option<String>,
+
+    /// Execute transactions in debug mode
+    #[clap(long, env = "MIDEN_DEBUG_MODE", global = true)]
+    pub debug_mode: Option<bool>,
+
+    /// Remote prover endpoint, transactions are proven locally when not set
+    #[clap(long, env = "MIDEN_PROVER_ENDPOINT", global = true)]
+    pub prover_endpoint: Option<String>,
+}
+
+impl ClientArgs {
+    /// Returns the client config: the config file, then the network defaults, then the flags.
+    pub fn config(&self) -> Result<ClientConfig, ConfigError> {
+        let overrides = ClientOverrides {
+            network: self.network,
+            rpc_endpoint: self.rpc_endpoint.clone(),
+            rpc_timeout_ms: self.rpc_timeout_ms,
+            store_path: self.store_path.clone(),
+            debug_mode: self.debug_mode,
+            prover_endpoint: self.prover_endpoint.clone(),
+        };
+        overrides.config(&self.client_config)
+    }
 }
 
 impl Cli {
-    pub async fn execute(&self) -> Result<(), String> {
-        // Setup client
-        let mut client = setup_client().await;
+    pub async fn execute(&self) -> Result<(), CliError> {
+        let config = self.client.config()?;
 
-        // Execute Cli commands
+        // Init and Market do not need the client, the other commands set it up first
         match &self.action {
-            Command::Setup(setup) => setup.execute(&mut client).await,
-            Command::Order(order) => order.execute(&mut client).await,
-            Command::Sync(sync) => sync.execute(&mut client).await,
-            Command::Init(init) => init.execute(),
-            Command::Query(query) => query.execute(&mut client).await,
-            Command::List(list) => list.execute(&client).await,
+            Command::Init(init) => init.execute(&config),
+            Command::Market(market) => market.execute(),
+            Command::Setup(setup) => {
+                let mut client = setup_client(&config).await?;
+                setup.execute(&mut client, &config).await
+            }
+            Command::Order(order) => order.execute(&mut setup_client(&config).await?).await,
+            Command::Cancel(cancel) => cancel.execute(&mut setup_client(&config).await?).await,
+            Command::Sync(sync) => sync.execute(&mut setup_client(&config).await?).await,
+            Command::Query(query) => query.execute(&mut setup_client(&config).await?).await,
+            Command::List(list) => list.execute(&setup_client(&config).await?).await,
         }
     }
 }
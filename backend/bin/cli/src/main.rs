@@ -1,11 +1,20 @@
+use std::process::ExitCode;
+
 use clap::Parser;
+use miden_order_book::errors::report;
 use miden_order_book_cli::Cli;
 
 #[tokio::main]
-async fn main() -> Result<(), String> {
+async fn main() -> ExitCode {
     env_logger::init();
 
     let cli = Cli::parse();
 
-    cli.execute().await
+    match cli.execute().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", report(&err));
+            err.exit_code()
+        }
+    }
 }
@@ -0,0 +1,420 @@
+use std::{pin::Pin, sync::Arc};
+
+use miden_client::{accounts::AccountId, notes::NoteId};
+use miden_order_book::{
+    errors::OrderError,
+    order::{execute_order, Execution, MarketOrder, Order},
+};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+use tokio_stream::{wrappers::BroadcastStream, Stream, StreamExt};
+use tonic::{Request, Response, Status};
+
+use crate::{
+    proto::generated::{
+        order_book_server::OrderBook, CancelAllOrdersRequest, CancelAllOrdersResponse,
+        CancelOrderRequest, CancelOrderResponse, GetBookRequest, GetBookResponse, QuoteFillRequest,
+        QuoteFillResponse, StreamTradesRequest, SubmitMarketOrderRequest, SubmitOrderRequest,
+        SubmitOrderResponse, Trade,
+    },
+    service::Command,
+    state::{self, BookState},
+};
+
+// Order Book API
+// ================================================================================================
+
+/// gRPC service answering queries from the in-memory [BookState] and forwarding the requests
+/// which need the client to the order book service.
+///
+/// Requests are not authenticated: the accounts managed by the server trust every caller of the
+/// API to submit and cancel their orders, which is why it only listens on a loopback address
+/// unless told otherwise.
+pub struct OrderBookApi {
+    state: Arc<RwLock<BookState>>,
+    trades: broadcast::Sender<state::Trade>,
+    commands: mpsc::Sender<Command>,
+}
+
+impl OrderBookApi {
+    pub fn new(
+        state: Arc<RwLock<BookState>>,
+        trades: broadcast::Sender<state::Trade>,
+        commands: mpsc::Sender<Command>,
+    ) -> Self {
+        Self {
+            state,
+            trades,
+            commands,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl OrderBook for OrderBookApi {
+    type StreamTradesStream = Pin<Box<dyn Stream<Item = Result<Trade, Status>> + Send>>;
+
+    async fn submit_order(
+        &self,
+        request: Request<SubmitOrderRequest>,
+    ) -> Result<Response<SubmitOrderResponse>, Status> {
+        let request = request.into_inner();
+        let execution = request.execution().into();
+        let self_trade_prevention = request.self_trade_prevention().into();
+        let account_id: AccountId = request
+            .account_id
+            .ok_or(Status::invalid_argument("Missing account id"))?
+            .try_into()?;
+        let order: Order = request
+            .order
+            .ok_or(Status::invalid_argument("Missing order"))?
+            .try_into()?;
+
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::SubmitOrder {
+                account_id,
+                order,
+                execution,
+                self_trade_prevention,
+                reply,
+            })
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?;
+
+        let submission = response
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?
+            .map_err(Status::from)?;
+
+        Ok(Response::new(SubmitOrderResponse {
+            fills: submission.plan.fills.into_iter().map(Into::into).collect(),
+            posted_note_id: submission.posted_note_id.map(Into::into),
+            transaction_id: submission.transaction_id,
+            cancelled_orders: submission
+                .cancelled_orders
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }))
+    }
+
+    async fn submit_market_order(
+        &self,
+        request: Request<SubmitMarketOrderRequest>,
+    ) -> Result<Response<SubmitOrderResponse>, Status> {
+        let mut request = request.into_inner();
+        let account_id: AccountId = request
+            .account_id
+            .take()
+            .ok_or(Status::invalid_argument("Missing account id"))?
+            .try_into()?;
+        let order: MarketOrder = request.try_into()?;
+
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::SubmitMarketOrder {
+                account_id,
+                order,
+                reply,
+            })
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?;
+
+        let submission = response
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?
+            .map_err(Status::from)?;
+
+        Ok(Response::new(SubmitOrderResponse {
+            fills: submission.plan.fills.into_iter().map(Into::into).collect(),
+            posted_note_id: submission.posted_note_id.map(Into::into),
+            transaction_id: submission.transaction_id,
+            cancelled_orders: submission
+                .cancelled_orders
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+        }))
+    }
+
+    async fn cancel_order(
+        &self,
+        request: Request<CancelOrderRequest>,
+    ) -> Result<Response<CancelOrderResponse>, Status> {
+        let request = request.into_inner();
+        let account_id: AccountId = request
+            .account_id
+            .ok_or(Status::invalid_argument("Missing account id"))?
+            .try_into()?;
+        let note_id: NoteId = request
+            .note_id
+            .ok_or(Status::invalid_argument("Missing note id"))?
+            .try_into()?;
+
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::CancelOrder {
+                account_id,
+                note_id,
+                reply,
+            })
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?;
+
+        let cancellation = response
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CancelOrderResponse {
+            transaction_id: cancellation.transaction_id.unwrap_or_default(),
+        }))
+    }
+
+    async fn cancel_all_orders(
+        &self,
+        request: Request<CancelAllOrdersRequest>,
+    ) -> Result<Response<CancelAllOrdersResponse>, Status> {
+        let request = request.into_inner();
+        let account_id: AccountId = request
+            .account_id
+            .ok_or(Status::invalid_argument("Missing account id"))?
+            .try_into()?;
+        let pair: Option<(AccountId, AccountId)> =
+            request.pair.map(TryInto::try_into).transpose()?;
+
+        let (reply, response) = oneshot::channel();
+        self.commands
+            .send(Command::CancelAllOrders {
+                account_id,
+                pair,
+                reply,
+            })
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?;
+
+        let cancellation = response
+            .await
+            .map_err(|_| Status::unavailable("Order book service is not running"))?
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CancelAllOrdersResponse {
+            orders: cancellation.orders.into_iter().map(Into::into).collect(),
+            transaction_id: cancellation.transaction_id,
+        }))
+    }
+
+    async fn get_book(
+        &self,
+        request: Request<GetBookRequest>,
+    ) -> Result<Response<GetBookResponse>, Status> {
+        let (base_faucet_id, quote_faucet_id): (AccountId, AccountId) = request
+            .into_inner()
+            .pair
+            .ok_or(Status::invalid_argument("Missing pair"))?
+            .try_into()?;
+
+        let state = self.state.read().await;
+        let Some(book) = state.book(base_faucet_id, quote_faucet_id) else {
+            return Err(Status::not_found("Pair is not tracked"));
+        };
+
+        let asks = book.asks().map(|order| (*order).into()).collect();
+        let bids = book.bids().map(|order| (*order).into()).collect();
+        // the book may be kept with the other faucet as base, in which case its sides are swapped
+        let (asks, bids) = if book.base_faucet_id() == base_faucet_id {
+            (asks, bids)
+        } else {
+            (bids, asks)
+        };
+
+        Ok(Response::new(GetBookResponse {
+            block_num: state.block_num(),
+            asks,
+            bids,
+        }))
+    }
+
+    async fn quote_fill(
+        &self,
+        request: Request<QuoteFillRequest>,
+    ) -> Result<Response<QuoteFillResponse>, Status> {
+        let request = request.into_inner();
+        let execution: Execution = request.execution().into();
+        let incoming_order: Order = request
+            .order
+            .ok_or(Status::invalid_argument("Missing order"))?
+            .try_into()?;
+
+        let state = self.state.read().await;
+        let Some(book) = state.book_of(&incoming_order) else {
+            return Ok(Response::new(QuoteFillResponse { fills: Vec::new() }));
+        };
+
+        let fills = match execute_order(incoming_order, book, state.block_num(), execution) {
+            Ok((plan, _)) => plan.fills.into_iter().map(Into::into).collect(),
+            Err(OrderError::FailedFill(_)) => Vec::new(),
+            Err(err @ (OrderError::NotFullyFilled(..) | OrderError::WouldCross(_))) => {
+                return Err(Status::failed_precondition(err.to_string()))
+            }
+            Err(err) => return Err(Status::internal(err.to_string())),
+        };
+
+        Ok(Response::new(QuoteFillResponse { fills }))
+    }
+
+    async fn stream_trades(
+        &self,
+        request: Request<StreamTradesRequest>,
+    ) -> Result<Response<Self::StreamTradesStream>, Status> {
+        let pair: Option<(AccountId, AccountId)> = request
+            .into_inner()
+            .pair
+            .map(TryInto::try_into)
+            .transpose()?;
+
+        let trades =
+            BroadcastStream::new(self.trades.subscribe()).filter_map(move |trade| match trade {
+                Ok(trade) => match pair {
+                    Some((base_faucet_id, quote_faucet_id))
+                        if !trade.is_in_pair(base_faucet_id, quote_faucet_id) =>
+                    {
+                        None
+                    }
+                    _ => Some(Ok(trade.into())),
+                },
+                Err(err) => Some(Err(Status::data_loss(err.to_string()))),
+            });
+
+        Ok(Response::new(Box::pin(trades)))
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use miden_client::{
+        accounts::AccountId,
+        assets::{Asset, FungibleAsset},
+        notes::{build_swap_tag, NoteId, NoteType},
+    };
+    use miden_order_book::order::{NoteFill, Order};
+    use tokio::sync::{broadcast, mpsc, RwLock};
+    use tokio_stream::StreamExt;
+    use tonic::Request;
+
+    use super::OrderBookApi;
+    use crate::{
+        proto::generated::{
+            self, order_book_server::OrderBook, GetBookRequest, Pair, QuoteFillRequest,
+            StreamTradesRequest,
+        },
+        state::{self, BookState},
+    };
+
+    fn build_api() -> (OrderBookApi, Order) {
+        let source_faucet_id = AccountId::from_hex("0x227bd163275aa1bf").unwrap();
+        let target_faucet_id = AccountId::from_hex("0x2540b08edc3b087d").unwrap();
+        let note_id = NoteId::try_from_hex(
+            "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+        )
+        .unwrap();
+
+        let source_asset = Asset::Fungible(FungibleAsset::new(source_faucet_id, 10).unwrap());
+        let target_asset = Asset::Fungible(FungibleAsset::new(target_faucet_id, 20).unwrap());
+
+        // resting order offering the target asset and requesting the source asset
+        let existing_order = Order::new(Some(note_id), target_asset, source_asset);
+        let tag = build_swap_tag(NoteType::Public, &target_asset, &source_asset).unwrap();
+
+        let mut state = BookState::default();
+        state.update(7, tag, vec![existing_order]);
+
+        let (trades, _) = broadcast::channel(16);
+        let (commands, _) = mpsc::channel(16);
+
+        (
+            OrderBookApi::new(Arc::new(RwLock::new(state)), trades, commands),
+            existing_order,
+        )
+    }
+
+    #[tokio::test]
+    async fn get_book_returns_tracked_orders() {
+        let (api, existing_order) = build_api();
+        let pair = Pair {
+            base_faucet_id: Some(existing_order.source_asset().faucet_id().into()),
+            quote_faucet_id: Some(existing_order.target_asset().faucet_id().into()),
+        };
+
+        let response = api
+            .get_book(Request::new(GetBookRequest { pair: Some(pair) }))
+            .await
+            .unwrap();
+        let response = response.into_inner();
+
+        assert_eq!(response.block_num, 7);
+        assert_eq!(response.asks, vec![generated::Order::from(existing_order)]);
+        assert!(response.bids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn quote_fill_matches_incoming_order() {
+        let (api, existing_order) = build_api();
+        let incoming_order = Order::new(
+            None,
+            existing_order.target_asset(),
+            existing_order.source_asset(),
+        );
+
+        let response = api
+            .quote_fill(Request::new(QuoteFillRequest {
+                order: Some(incoming_order.into()),
+                execution: generated::Execution::Limit.into(),
+            }))
+            .await
+            .unwrap();
+        let fills = response.into_inner().fills;
+
+        assert_eq!(
+            fills,
+            vec![generated::Fill::from(
+                NoteFill::new(existing_order, 10).unwrap()
+            )]
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_trades_reports_consumed_orders() {
+        let (api, existing_order) = build_api();
+        let mut trades = api
+            .stream_trades(Request::new(StreamTradesRequest { pair: None }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // the note of the existing order is no longer found after the next sync
+        let tag = build_swap_tag(
+            NoteType::Public,
+            &existing_order.source_asset(),
+            &existing_order.target_asset(),
+        )
+        .unwrap();
+        for order in api.state.write().await.update(8, tag, Vec::new()) {
+            api.trades
+                .send(state::Trade {
+                    order,
+                    block_num: 8,
+                })
+                .unwrap();
+        }
+
+        let trade = trades.next().await.unwrap().unwrap();
+        assert_eq!(trade.order, Some(existing_order.into()));
+        assert_eq!(trade.block_num, 8);
+    }
+}
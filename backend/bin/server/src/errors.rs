@@ -0,0 +1,155 @@
+use std::{error::Error, fmt};
+
+use miden_client::{notes::NoteId, ClientError};
+use miden_order_book::errors::{
+    report, NoteIndexError, OrderError, SwappNoteError, TransactionError,
+};
+use tonic::Status;
+
+/// Errors of the commands executed by the order book service.
+///
+/// Errors of the library are shown as they are, the other variants add the context of the
+/// command which failed.
+#[derive(Debug)]
+pub enum ServiceError {
+    Order(OrderError),
+    Transaction(TransactionError),
+    NoteIndex(NoteIndexError),
+    Client(ClientError),
+    InvalidNote(NoteId, SwappNoteError),
+    UntrackedPair,
+    TransactionFailed(ClientError),
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ServiceError::Order(err) => write!(f, "{}", err),
+            ServiceError::Transaction(err) => write!(f, "{}", err),
+            ServiceError::NoteIndex(err) => write!(f, "{}", err),
+            ServiceError::Client(err) => write!(f, "{}", err),
+            ServiceError::InvalidNote(note_id, _) => {
+                write!(f, "Note {} is not a valid SWAPP note", note_id)
+            }
+            ServiceError::UntrackedPair => write!(f, "Pair is not tracked"),
+            ServiceError::TransactionFailed(_) => write!(f, "Failed to execute transaction"),
+        }
+    }
+}
+
+impl Error for ServiceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            // library errors are displayed as they are, so their sources are shown next
+            ServiceError::Order(err) => err.source(),
+            ServiceError::Transaction(err) => err.source(),
+            ServiceError::NoteIndex(err) => err.source(),
+            ServiceError::Client(err) => err.source(),
+            ServiceError::InvalidNote(_, err) => Some(err),
+            ServiceError::TransactionFailed(err) => Some(err),
+            ServiceError::UntrackedPair => None,
+        }
+    }
+}
+
+impl From<OrderError> for ServiceError {
+    fn from(err: OrderError) -> Self {
+        ServiceError::Order(err)
+    }
+}
+
+impl From<TransactionError> for ServiceError {
+    fn from(err: TransactionError) -> Self {
+        ServiceError::Transaction(err)
+    }
+}
+
+impl From<NoteIndexError> for ServiceError {
+    fn from(err: NoteIndexError) -> Self {
+        ServiceError::NoteIndex(err)
+    }
+}
+
+impl From<ClientError> for ServiceError {
+    fn from(err: ClientError) -> Self {
+        ServiceError::Client(err)
+    }
+}
+
+/// Rejected orders and requests fail with the status of their cause, failures of the client or
+/// of the node are internal errors.
+impl From<ServiceError> for Status {
+    fn from(err: ServiceError) -> Self {
+        let message = report(&err);
+        match err {
+            ServiceError::Order(
+                OrderError::AssetsNotMatching
+                | OrderError::MissingId
+                | OrderError::InvalidFaucet(_)
+                | OrderError::ZeroAmount
+                | OrderError::InvalidFillAmount(..),
+            )
+            | ServiceError::InvalidNote(..) => Status::invalid_argument(message),
+            ServiceError::Order(OrderError::FailedFill(_) | OrderError::InternalError(_)) => {
+                Status::internal(message)
+            }
+            ServiceError::Order(_)
+            | ServiceError::Transaction(TransactionError::NothingToReclaim) => {
+                Status::failed_precondition(message)
+            }
+            ServiceError::Transaction(TransactionError::NotCreator(..)) => {
+                Status::permission_denied(message)
+            }
+            ServiceError::UntrackedPair => Status::not_found(message),
+            ServiceError::Transaction(_)
+            | ServiceError::NoteIndex(_)
+            | ServiceError::Client(_)
+            | ServiceError::TransactionFailed(_) => Status::internal(message),
+        }
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use miden_client::notes::NoteId;
+    use miden_order_book::errors::{OrderError, SwappNoteError};
+    use tonic::{Code, Status};
+
+    use super::ServiceError;
+
+    #[test]
+    fn service_errors_map_to_status_codes() {
+        let code = |err: ServiceError| Status::from(err).code();
+
+        assert_eq!(
+            code(ServiceError::Order(OrderError::ZeroAmount)),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            code(ServiceError::Order(OrderError::NotFullyFilled(1, 2))),
+            Code::FailedPrecondition
+        );
+        assert_eq!(code(ServiceError::UntrackedPair), Code::NotFound);
+
+        let note_id = NoteId::try_from_hex(
+            "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+        )
+        .unwrap();
+        assert_eq!(
+            code(ServiceError::InvalidNote(
+                note_id,
+                SwappNoteError::MissingMetadata
+            )),
+            Code::InvalidArgument
+        );
+
+        let status = Status::from(ServiceError::Order(OrderError::InternalError(
+            "Book is corrupted".to_string(),
+        )));
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "Internal error: Book is corrupted");
+    }
+}
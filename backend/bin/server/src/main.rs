@@ -1,3 +1,186 @@
-fn main() {
-    println!("Hello, world!");
+mod api;
+mod errors;
+mod proto;
+mod service;
+mod state;
+
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use api::OrderBookApi;
+use clap::{Args, Parser};
+use log::info;
+use miden_client::notes::NoteTag;
+use miden_order_book::{
+    clob_data::ClobData,
+    config::{ClientConfig, ClientOverrides, Network},
+    constants::{CLIENT_CONFIG_FILE_PATH, CLOB_DATA_FILE_PATH, ORDER_BOOK_DB_FILE_PATH},
+    errors::{report, ConfigError},
+    store::OrderBookStore,
+    utils::setup_client,
+};
+use proto::generated::order_book_server::OrderBookServer;
+use service::OrderBookService;
+use state::BookState;
+use tokio::sync::{broadcast, mpsc, RwLock};
+use tonic::transport::Server;
+
+/// Server arguments
+#[derive(Parser, Debug)]
+#[clap(
+    name = "miden-order-book-server",
+    about = "Miden order book server",
+    version,
+    rename_all = "kebab-case"
+)]
+pub struct ServerArgs {
+    /// Swap tags of the SWAPP notes to track, defaults to the tags of the CLOB data
+    pub tags: Vec<u32>,
+
+    /// Markets whose SWAPP notes to track, BASE/QUOTE
+    #[clap(long = "market")]
+    pub markets: Vec<String>,
+
+    /// Address the gRPC API listens on, which must be a loopback address unless --allow-remote is
+    /// set
+    #[clap(long, default_value = "127.0.0.1:50051")]
+    pub listen: SocketAddr,
+
+    /// Accept to listen on a non-loopback address. Requests are not authenticated: anyone who
+    /// can reach the API can submit and cancel orders of the accounts managed by the server
+    #[clap(long)]
+    pub allow_remote: bool,
+
+    /// Path of the order book store
+    #[clap(long, default_value = ORDER_BOOK_DB_FILE_PATH)]
+    pub store: String,
+
+    /// Seconds between two syncs with the node, at least 1
+    #[clap(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub sync_interval: u64,
+
+    #[clap(flatten)]
+    pub client: ClientArgs,
+}
+
+/// Client flags, each of them can also be set through the environment.
+#[derive(Debug, Clone, Args)]
+pub struct ClientArgs {
+    /// Client config file
+    #[clap(
+        long = "client-config",
+        env = "MIDEN_CLIENT_CONFIG",
+        default_value = CLIENT_CONFIG_FILE_PATH,
+        global = true
+    )]
+    pub client_config: String,
+
+    /// Network to connect to, localhost, devnet or testnet, sets the default endpoint and store
+    #[clap(long, env = "MIDEN_NETWORK", global = true)]
+    pub network: Option<Network>,
+
+    /// Node RPC endpoint, protocol://host:port
+    #[clap(long, env = "MIDEN_RPC_ENDPOINT", global = true)]
+    pub rpc_endpoint: Option<String>,
+
+    /// Node RPC timeout in milliseconds
+    #[clap(long, env = "MIDEN_RPC_TIMEOUT_MS", global = true)]
+    pub rpc_timeout_ms: Option<u64>,
+
+    /// Path of the client store
+    #[clap(long, env = "MIDEN_STORE_PATH", global = true)]
+    pub store_path: Option<String>,
+
+    /// Execute transactions in debug mode
+    #[clap(long, env = "MIDEN_DEBUG_MODE", global = true)]
+    pub debug_mode: Option<bool>,
+
+    /// Remote prover endpoint, transactions are proven locally when not set
+    #[clap(long, env = "MIDEN_PROVER_ENDPOINT", global = true)]
+    pub prover_endpoint: Option<String>,
+}
+
+impl ClientArgs {
+    /// Returns the client config: the config file, then the network defaults, then the flags.
+    pub fn config(&self) -> Result<ClientConfig, ConfigError> {
+        let overrides = ClientOverrides {
+            network: self.network,
+            rpc_endpoint: self.rpc_endpoint.clone(),
+            rpc_timeout_ms: self.rpc_timeout_ms,
+            store_path: self.store_path.clone(),
+            debug_mode: self.debug_mode,
+            prover_endpoint: self.prover_endpoint.clone(),
+        };
+        overrides.config(&self.client_config)
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), String> {
+    env_logger::init();
+
+    let args = ServerArgs::parse();
+    if !args.listen.ip().is_loopback() && !args.allow_remote {
+        return Err(format!(
+            "Refusing to serve the unauthenticated API on {}, use --allow-remote to do so",
+            args.listen
+        ));
+    }
+
+    // Setup client
+    let config = args.client.config().map_err(|e| report(&e))?;
+    let mut client = setup_client(&config).await.map_err(|e| report(&e))?;
+
+    let clob_data = ClobData::load(CLOB_DATA_FILE_PATH).map_err(|e| report(&e))?;
+    let tags: Vec<NoteTag> = if args.markets.is_empty() {
+        clob_data
+            .tags_or_default(&args.tags)
+            .map_err(|e| report(&e))?
+    } else {
+        let mut tags: Vec<NoteTag> = args.tags.iter().map(|tag| NoteTag::from(*tag)).collect();
+        for symbol in args.markets.iter() {
+            let market = clob_data.markets.get(symbol).map_err(|e| report(&e))?;
+            tags.extend([market.bid_tag(), market.ask_tag()]);
+        }
+        tags
+    };
+    for tag in tags.iter() {
+        client.add_note_tag(*tag).await.map_err(|e| report(&e))?;
+    }
+
+    let state = Arc::new(RwLock::new(BookState::default()));
+    let (trades, _) = broadcast::channel(1024);
+    let (commands, receiver) = mpsc::channel(64);
+
+    let api = OrderBookServer::new(OrderBookApi::new(state.clone(), trades.clone(), commands));
+    let store = OrderBookStore::open(&args.store).map_err(|e| report(&e))?;
+    let service = OrderBookService::new(client, store, state, trades, tags);
+    service.restore().await?;
+
+    info!("Order book server listening on {}", args.listen);
+
+    // Serve the API while the service keeps the book in sync and executes orders
+    tokio::select! {
+        result = Server::builder().add_service(api).serve(args.listen) => {
+            result.map_err(|e| format!("Failed to serve order book API: {}", e))
+        },
+        result = service.run(receiver, Duration::from_secs(args.sync_interval)) => result,
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::ServerArgs;
+
+    #[test]
+    fn sync_interval_must_be_positive() {
+        let args = ServerArgs::try_parse_from(["server", "--sync-interval", "1"]).unwrap();
+        assert_eq!(args.sync_interval, 1);
+
+        assert!(ServerArgs::try_parse_from(["server", "--sync-interval", "0"]).is_err());
+    }
 }
@@ -0,0 +1,248 @@
+use miden_client::{
+    accounts::AccountId,
+    assets::{Asset, FungibleAsset},
+    notes::NoteId,
+    transactions::NoteArgs,
+    Felt, Word,
+};
+use miden_objects::Digest;
+use miden_order_book::{
+    order::{
+        Execution, MarketAmount, MarketOrder, NoteFill, Order, OrderValidity, SelfTradePrevention,
+        SlippageGuard,
+    },
+    price::Price,
+};
+use tonic::Status;
+
+use crate::state::Trade;
+
+pub mod generated {
+    tonic::include_proto!("order_book");
+}
+
+// Conversions
+// ================================================================================================
+
+impl From<AccountId> for generated::AccountId {
+    fn from(value: AccountId) -> Self {
+        Self { id: value.into() }
+    }
+}
+
+impl TryFrom<generated::AccountId> for AccountId {
+    type Error = Status;
+
+    fn try_from(value: generated::AccountId) -> Result<Self, Self::Error> {
+        AccountId::try_from(value.id)
+            .map_err(|e| Status::invalid_argument(format!("Invalid account id: {}", e)))
+    }
+}
+
+impl From<NoteId> for generated::NoteId {
+    fn from(value: NoteId) -> Self {
+        let word: Word = value.inner().into();
+        let [d0, d1, d2, d3] = word.map(|felt| felt.as_int());
+        Self { d0, d1, d2, d3 }
+    }
+}
+
+impl TryFrom<generated::NoteId> for NoteId {
+    type Error = Status;
+
+    fn try_from(value: generated::NoteId) -> Result<Self, Self::Error> {
+        let word = to_word([value.d0, value.d1, value.d2, value.d3])?;
+        Ok(NoteId::from(Digest::from(word)))
+    }
+}
+
+impl From<NoteArgs> for generated::NoteArgs {
+    fn from(value: NoteArgs) -> Self {
+        let [d0, d1, d2, d3] = value.map(|felt| felt.as_int());
+        Self { d0, d1, d2, d3 }
+    }
+}
+
+impl TryFrom<generated::NoteArgs> for NoteArgs {
+    type Error = Status;
+
+    fn try_from(value: generated::NoteArgs) -> Result<Self, Self::Error> {
+        to_word([value.d0, value.d1, value.d2, value.d3])
+    }
+}
+
+impl From<Asset> for generated::Asset {
+    fn from(value: Asset) -> Self {
+        Self {
+            faucet_id: Some(value.faucet_id().into()),
+            amount: value.unwrap_fungible().amount(),
+        }
+    }
+}
+
+impl TryFrom<generated::Asset> for Asset {
+    type Error = Status;
+
+    fn try_from(value: generated::Asset) -> Result<Self, Self::Error> {
+        let faucet_id = value
+            .faucet_id
+            .ok_or(Status::invalid_argument("Missing faucet id"))?
+            .try_into()?;
+        let asset = FungibleAsset::new(faucet_id, value.amount)
+            .map_err(|e| Status::invalid_argument(format!("Invalid asset: {}", e)))?;
+
+        Ok(Asset::Fungible(asset))
+    }
+}
+
+impl From<generated::Price> for Price {
+    fn from(value: generated::Price) -> Self {
+        Price::new(value.offered, value.requested)
+    }
+}
+
+impl TryFrom<generated::SubmitMarketOrderRequest> for MarketOrder {
+    type Error = Status;
+
+    fn try_from(value: generated::SubmitMarketOrderRequest) -> Result<Self, Self::Error> {
+        use generated::submit_market_order_request::{Amount, Guard};
+
+        let source_faucet_id = value
+            .source_faucet_id
+            .ok_or(Status::invalid_argument("Missing source faucet id"))?
+            .try_into()?;
+        let target_faucet_id = value
+            .target_faucet_id
+            .ok_or(Status::invalid_argument("Missing target faucet id"))?
+            .try_into()?;
+        let amount = match value.amount {
+            Some(Amount::Spend(0) | Amount::Receive(0)) => {
+                return Err(Status::invalid_argument("Amount must be positive"))
+            }
+            Some(Amount::Spend(amount)) => MarketAmount::Spend(amount),
+            Some(Amount::Receive(amount)) => MarketAmount::Receive(amount),
+            None => return Err(Status::invalid_argument("Missing amount")),
+        };
+        let guard = match value.guard {
+            Some(Guard::MaxAveragePrice(price)) => SlippageGuard::MaxAveragePrice(price.into()),
+            Some(Guard::MaxSlippageBps(bps)) => SlippageGuard::MaxSlippageBps(bps),
+            None => return Err(Status::invalid_argument("Missing slippage guard")),
+        };
+
+        Ok(MarketOrder {
+            source_faucet_id,
+            target_faucet_id,
+            amount,
+            guard,
+            // set to the account executing the order by the service
+            account_id: None,
+        })
+    }
+}
+
+impl From<generated::Execution> for Execution {
+    fn from(value: generated::Execution) -> Self {
+        match value {
+            generated::Execution::Limit => Execution::Limit,
+            generated::Execution::FillOrKill => Execution::FillOrKill,
+            generated::Execution::PostOnly => Execution::PostOnly,
+        }
+    }
+}
+
+impl From<generated::SelfTradePrevention> for SelfTradePrevention {
+    fn from(value: generated::SelfTradePrevention) -> Self {
+        match value {
+            generated::SelfTradePrevention::Skip => SelfTradePrevention::Skip,
+            generated::SelfTradePrevention::CancelResting => SelfTradePrevention::CancelResting,
+            generated::SelfTradePrevention::CancelIncoming => SelfTradePrevention::CancelIncoming,
+        }
+    }
+}
+
+impl From<Order> for generated::Order {
+    fn from(value: Order) -> Self {
+        Self {
+            note_id: value.id().map(Into::into),
+            source_asset: Some(value.source_asset().into()),
+            target_asset: Some(value.target_asset().into()),
+            block_num: value.block_num(),
+            good_after_block: value.validity().good_after_block,
+            expiry_block: value.validity().expiry_block,
+        }
+    }
+}
+
+impl TryFrom<generated::Order> for Order {
+    type Error = Status;
+
+    fn try_from(value: generated::Order) -> Result<Self, Self::Error> {
+        let id = value.note_id.map(NoteId::try_from).transpose()?;
+        let source_asset = value
+            .source_asset
+            .ok_or(Status::invalid_argument("Missing source asset"))?
+            .try_into()?;
+        let target_asset = value
+            .target_asset
+            .ok_or(Status::invalid_argument("Missing target asset"))?
+            .try_into()?;
+
+        let order = Order::new(id, source_asset, target_asset).with_validity(OrderValidity::new(
+            value.good_after_block,
+            value.expiry_block,
+        ));
+
+        Ok(match value.block_num {
+            Some(block_num) => order.with_block_num(block_num),
+            None => order,
+        })
+    }
+}
+
+impl From<NoteFill> for generated::Fill {
+    fn from(value: NoteFill) -> Self {
+        Self {
+            order: Some(value.order.into()),
+            args: Some(value.args().into()),
+        }
+    }
+}
+
+impl From<Trade> for generated::Trade {
+    fn from(value: Trade) -> Self {
+        Self {
+            order: Some(value.order.into()),
+            block_num: value.block_num,
+        }
+    }
+}
+
+impl TryFrom<generated::Pair> for (AccountId, AccountId) {
+    type Error = Status;
+
+    fn try_from(value: generated::Pair) -> Result<Self, Self::Error> {
+        let base_faucet_id = value
+            .base_faucet_id
+            .ok_or(Status::invalid_argument("Missing base faucet id"))?
+            .try_into()?;
+        let quote_faucet_id = value
+            .quote_faucet_id
+            .ok_or(Status::invalid_argument("Missing quote faucet id"))?
+            .try_into()?;
+
+        Ok((base_faucet_id, quote_faucet_id))
+    }
+}
+
+// Helpers
+// ================================================================================================
+
+fn to_word(values: [u64; 4]) -> Result<Word, Status> {
+    let mut word = [Felt::new(0); 4];
+    for (felt, value) in word.iter_mut().zip(values) {
+        *felt = Felt::try_from(value)
+            .map_err(|e| Status::invalid_argument(format!("Invalid field element: {}", e)))?;
+    }
+
+    Ok(word)
+}
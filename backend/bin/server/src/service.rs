@@ -0,0 +1,1088 @@
+use std::{sync::Arc, time::Duration};
+
+use log::{error, info, warn};
+use miden_client::{
+    accounts::AccountId,
+    crypto::FeltRng,
+    notes::{Note, NoteId, NoteTag, NoteType},
+    transactions::TransactionRequest,
+    Client, ClientError, ZERO,
+};
+use miden_order_book::{
+    book::OrderBook,
+    errors::{report, NoteIndexError, OrderError, SwappNoteError, TransactionError},
+    note::{
+        create_expected_partial_swapp_note, create_fill_transaction_request,
+        create_reclaim_transaction_request, create_swapp_note,
+    },
+    order::{
+        execute_order, fill_market_order, prevent_self_trades, Execution, FillPlan, MarketOrder,
+        Order, SelfTradePrevention,
+    },
+    store::{OrderBookStore, OrderStatus},
+    utils::{
+        get_note_consumer, get_orders_by_tag, get_swapp_notes_by_creator, note_from_record,
+        sync_state,
+    },
+};
+use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+
+use crate::{
+    errors::ServiceError,
+    state::{BookState, Trade},
+};
+
+// Commands
+// ================================================================================================
+
+/// Requests which need the client, and are therefore executed by the service loop.
+pub enum Command {
+    SubmitOrder {
+        account_id: AccountId,
+        order: Order,
+        execution: Execution,
+        self_trade_prevention: SelfTradePrevention,
+        reply: oneshot::Sender<Result<Submission, ServiceError>>,
+    },
+    SubmitMarketOrder {
+        account_id: AccountId,
+        order: MarketOrder,
+        reply: oneshot::Sender<Result<Submission, ServiceError>>,
+    },
+    CancelOrder {
+        account_id: AccountId,
+        note_id: NoteId,
+        reply: oneshot::Sender<Result<Cancellation, ServiceError>>,
+    },
+    CancelAllOrders {
+        account_id: AccountId,
+        /// Only cancel the orders of this pair, in either direction.
+        pair: Option<(AccountId, AccountId)>,
+        reply: oneshot::Sender<Result<Cancellation, ServiceError>>,
+    },
+}
+
+/// Outcome of a submitted order.
+pub struct Submission {
+    /// Orders consumed by the transaction along with their fill amounts.
+    pub plan: FillPlan,
+    /// SWAPP note posted for the unfilled part of the order, all of it when nothing could be
+    /// filled.
+    pub posted_note_id: Option<NoteId>,
+    /// Resting orders of the account reclaimed by the transaction.
+    pub cancelled_orders: Vec<Order>,
+    pub transaction_id: String,
+}
+
+/// Outcome of a cancellation.
+pub struct Cancellation {
+    /// Orders whose SWAPP notes were reclaimed.
+    pub orders: Vec<Order>,
+    /// Not set when there was nothing to cancel.
+    pub transaction_id: Option<String>,
+}
+
+// Order Book Service
+// ================================================================================================
+
+/// Owns the client and the order book store: keeps the book in sync with the node and executes
+/// the commands received from the API.
+pub struct OrderBookService<C: OrderBookClient> {
+    client: C,
+    store: OrderBookStore,
+    state: Arc<RwLock<BookState>>,
+    trades: broadcast::Sender<Trade>,
+    tags: Vec<NoteTag>,
+}
+
+impl<C: OrderBookClient> OrderBookService<C> {
+    pub fn new(
+        client: C,
+        store: OrderBookStore,
+        state: Arc<RwLock<BookState>>,
+        trades: broadcast::Sender<Trade>,
+        tags: Vec<NoteTag>,
+    ) -> Self {
+        Self {
+            client,
+            store,
+            state,
+            trades,
+            tags,
+        }
+    }
+
+    /// Fills the book with the open orders persisted by a previous run.
+    pub async fn restore(&self) -> Result<(), String> {
+        let records = self
+            .store
+            .get_orders(OrderStatus::Open)
+            .map_err(|e| report(&e))?;
+
+        let mut state = self.state.write().await;
+        for tag in self.tags.iter() {
+            let orders = records
+                .iter()
+                .filter(|record| record.tag == *tag)
+                .map(|record| record.order)
+                .collect();
+            state.update(0, *tag, orders);
+        }
+        info!("Restored {} open orders", records.len());
+
+        Ok(())
+    }
+
+    /// Syncs with the node every `interval` and executes commands in between.
+    pub async fn run(
+        mut self,
+        mut commands: mpsc::Receiver<Command>,
+        interval: Duration,
+    ) -> Result<(), String> {
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => self.sync().await,
+                command = commands.recv() => match command {
+                    Some(command) => self.execute(command).await,
+                    None => return Err("Command channel closed".to_string()),
+                },
+            }
+        }
+    }
+
+    /// Syncs the client and refreshes the book with the unspent SWAPP notes of every tracked tag.
+    async fn sync(&mut self) {
+        let block_num = match self.client.sync(&mut self.store).await {
+            Ok(block_num) => block_num,
+            Err(err) => {
+                warn!("Failed to sync state: {}", report(&err));
+                return;
+            }
+        };
+
+        for tag in self.tags.clone() {
+            let result = self.client.get_orders_by_tag(&mut self.store, tag).await;
+            let (orders, rejected) = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    warn!("Failed to get notes of tag {}: {}", tag, report(&err));
+                    continue;
+                }
+            };
+            for (note_id, err) in rejected.iter() {
+                warn!(
+                    "Skipping note {}, not a valid SWAPP note: {}",
+                    note_id,
+                    report(err)
+                );
+            }
+            info!("Tracking {} orders for tag {}", orders.len(), tag);
+
+            for order in orders.iter() {
+                if let Err(err) = self.store.insert_order(*order, None, block_num) {
+                    error!("Failed to store order {:?}: {}", order.id(), err);
+                }
+            }
+
+            let consumed_orders = self.state.write().await.update(block_num, tag, orders);
+            for order in consumed_orders {
+                if self.is_reclaimed(&order).await {
+                    info!("Order {:?} was cancelled by its creator", order.id());
+                    if let Err(err) = self.store.record_cancels(&[order], block_num) {
+                        error!("Failed to store cancel of order {:?}: {}", order.id(), err);
+                    }
+                    continue;
+                }
+
+                let trade = Trade { order, block_num };
+                self.record_trade(trade);
+                // no subscribers is not an error
+                let _ = self.trades.send(trade);
+            }
+        }
+    }
+
+    async fn execute(&mut self, command: Command) {
+        match command {
+            Command::SubmitOrder {
+                account_id,
+                order,
+                execution,
+                self_trade_prevention,
+                reply,
+            } => {
+                let result = self
+                    .submit_order(account_id, order, execution, self_trade_prevention)
+                    .await;
+                // the client may have gone away, the order was processed anyway
+                let _ = reply.send(result);
+            }
+            Command::SubmitMarketOrder {
+                account_id,
+                order,
+                reply,
+            } => {
+                let result = self.submit_market_order(account_id, order).await;
+                let _ = reply.send(result);
+            }
+            Command::CancelOrder {
+                account_id,
+                note_id,
+                reply,
+            } => {
+                let result = match self.client.get_note(note_id).await {
+                    Ok(note) => self.cancel_orders(account_id, vec![note]).await,
+                    Err(err) => Err(err),
+                };
+                let _ = reply.send(result);
+            }
+            Command::CancelAllOrders {
+                account_id,
+                pair,
+                reply,
+            } => {
+                let result = self.cancel_all_orders(account_id, pair).await;
+                let _ = reply.send(result);
+            }
+        }
+    }
+
+    /// Fills the incoming order against the book with the semantics of `execution`, and posts its
+    /// unfilled part as a SWAPP note in the same transaction. Resting orders of the account are
+    /// handled per `self_trade_prevention`.
+    async fn submit_order(
+        &mut self,
+        account_id: AccountId,
+        incoming_order: Order,
+        execution: Execution,
+        self_trade_prevention: SelfTradePrevention,
+    ) -> Result<Submission, ServiceError> {
+        let incoming_order = incoming_order.with_creator(account_id);
+        let (cancelled_orders, fill) = {
+            let state = self.state.read().await;
+            match state.book_of(&incoming_order) {
+                Some(book) => {
+                    let block_num = state.block_num();
+                    let cancelled_orders = prevent_self_trades(
+                        incoming_order,
+                        book,
+                        block_num,
+                        self_trade_prevention,
+                    )?;
+                    (
+                        cancelled_orders,
+                        execute_order(incoming_order, book, block_num, execution),
+                    )
+                }
+                None if execution == Execution::FillOrKill => (
+                    Vec::new(),
+                    Err(OrderError::NotFullyFilled(
+                        0,
+                        incoming_order.source_asset().unwrap_fungible().amount(),
+                    )),
+                ),
+                None => (Vec::new(), Err(OrderError::FailedFill(incoming_order))),
+            }
+        };
+
+        let (transaction_request, plan, successor, posted_note_id) = match fill {
+            Ok((plan, remaining_source)) => {
+                let expected_partial_swapp = self
+                    .get_expected_partial_swapp_note(account_id, &plan)
+                    .await?;
+
+                // the unfilled part of the order is posted in the same transaction
+                let remainder_swapp = match incoming_order.remainder(remaining_source) {
+                    Some(remainder) => Some(
+                        create_swapp_note(
+                            account_id,
+                            remainder.source_asset(),
+                            remainder.target_asset(),
+                            NoteType::Public,
+                            ZERO,
+                            remainder.validity(),
+                            self.client.rng(),
+                        )
+                        .map_err(TransactionError::from)?,
+                    ),
+                    None => None,
+                };
+                let posted_note_id = remainder_swapp.as_ref().map(Note::id);
+
+                let successor = get_successor_order(expected_partial_swapp.as_ref())?;
+                let transaction_request = create_fill_transaction_request(
+                    &plan,
+                    &cancelled_orders,
+                    expected_partial_swapp,
+                    remainder_swapp,
+                )?;
+
+                (transaction_request, plan, successor, posted_note_id)
+            }
+            Err(OrderError::FailedFill(order)) => {
+                let note = create_swapp_note(
+                    account_id,
+                    order.source_asset(),
+                    order.target_asset(),
+                    NoteType::Public,
+                    ZERO,
+                    order.validity(),
+                    self.client.rng(),
+                )
+                .map_err(TransactionError::from)?;
+                let note_id = note.id();
+
+                // own orders the posted order would match are reclaimed in the same transaction
+                let transaction_request = create_fill_transaction_request(
+                    &FillPlan::default(),
+                    &cancelled_orders,
+                    None,
+                    Some(note),
+                )?;
+
+                (
+                    transaction_request,
+                    FillPlan::default(),
+                    None,
+                    Some(note_id),
+                )
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        self.submit_fills(
+            account_id,
+            transaction_request,
+            plan,
+            successor,
+            posted_note_id,
+            cancelled_orders,
+        )
+        .await
+    }
+
+    /// Fills a market order against the book, within the bound on its average price.
+    async fn submit_market_order(
+        &mut self,
+        account_id: AccountId,
+        market_order: MarketOrder,
+    ) -> Result<Submission, ServiceError> {
+        let market_order = MarketOrder {
+            account_id: Some(account_id),
+            ..market_order
+        };
+        let plan = {
+            let state = self.state.read().await;
+            let book = state
+                .book(market_order.source_faucet_id, market_order.target_faucet_id)
+                .ok_or(ServiceError::UntrackedPair)?;
+            fill_market_order(market_order, book, state.block_num())?
+        };
+
+        let expected_partial_swapp = self
+            .get_expected_partial_swapp_note(account_id, &plan)
+            .await?;
+        let successor = get_successor_order(expected_partial_swapp.as_ref())?;
+        let transaction_request =
+            create_fill_transaction_request(&plan, &[], expected_partial_swapp, None)?;
+
+        self.submit_fills(
+            account_id,
+            transaction_request,
+            plan,
+            successor,
+            None,
+            Vec::new(),
+        )
+        .await
+    }
+
+    /// Returns the successor SWAPP note created for the remainder of the last order of `plan`
+    /// when it is only partially filled.
+    async fn get_expected_partial_swapp_note(
+        &self,
+        account_id: AccountId,
+        plan: &FillPlan,
+    ) -> Result<Option<Note>, ServiceError> {
+        let Some(partial_fill) = plan.partial_fill() else {
+            return Ok(None);
+        };
+        let Some(successor) = partial_fill.successor else {
+            return Ok(None);
+        };
+
+        let note = self.client.get_note(successor.parent_id).await?;
+        Ok(Some(create_expected_partial_swapp_note(
+            account_id,
+            note,
+            partial_fill.paid,
+        )?))
+    }
+
+    /// Executes and submits a transaction filling orders, and records its fills and the cancels of
+    /// the orders it reclaims.
+    async fn submit_fills(
+        &mut self,
+        account_id: AccountId,
+        transaction_request: TransactionRequest,
+        plan: FillPlan,
+        successor: Option<Order>,
+        posted_note_id: Option<NoteId>,
+        cancelled_orders: Vec<Order>,
+    ) -> Result<Submission, ServiceError> {
+        let transaction_id = self
+            .client
+            .execute_transaction(account_id, transaction_request)
+            .await
+            .map_err(ServiceError::TransactionFailed)?;
+
+        info!(
+            "Submitted transaction {} for account {}",
+            transaction_id, account_id
+        );
+
+        // reclaimed orders leave the book now, like cancelled orders
+        let block_num = {
+            let mut state = self.state.write().await;
+            for note_id in cancelled_orders.iter().filter_map(Order::id) {
+                state.remove(note_id);
+            }
+            state.block_num()
+        };
+        if let Err(err) = self.store.record_fills(&plan, successor, block_num) {
+            error!(
+                "Failed to store fills of transaction {}: {}",
+                transaction_id, err
+            );
+        }
+        if let Err(err) = self.store.record_cancels(&cancelled_orders, block_num) {
+            error!(
+                "Failed to store cancels of transaction {}: {}",
+                transaction_id, err
+            );
+        }
+
+        Ok(Submission {
+            plan,
+            posted_note_id,
+            cancelled_orders,
+            transaction_id,
+        })
+    }
+
+    /// Reclaims every tracked SWAPP note created by `account_id`, only the ones of the given pair
+    /// when it is set.
+    async fn cancel_all_orders(
+        &mut self,
+        account_id: AccountId,
+        pair: Option<(AccountId, AccountId)>,
+    ) -> Result<Cancellation, ServiceError> {
+        let tags = match pair {
+            Some((base_faucet_id, quote_faucet_id)) => {
+                let book = OrderBook::new(base_faucet_id, quote_faucet_id)?;
+                vec![book.bid_tag(), book.ask_tag()]
+            }
+            None => self.tags.clone(),
+        };
+
+        let notes = self
+            .client
+            .get_swapp_notes_by_creator(&mut self.store, account_id, &tags)
+            .await?;
+        self.cancel_orders(account_id, notes).await
+    }
+
+    /// Reclaims SWAPP notes created by `account_id` in a single transaction.
+    async fn cancel_orders(
+        &mut self,
+        account_id: AccountId,
+        notes: Vec<Note>,
+    ) -> Result<Cancellation, ServiceError> {
+        let orders = notes
+            .iter()
+            .map(|note| Order::try_from(note).map_err(|e| ServiceError::InvalidNote(note.id(), e)))
+            .collect::<Result<Vec<Order>, ServiceError>>()?;
+        if notes.is_empty() {
+            return Ok(Cancellation {
+                orders,
+                transaction_id: None,
+            });
+        }
+
+        let transaction_request = create_reclaim_transaction_request(account_id, &notes)?;
+        let transaction_id = self
+            .client
+            .execute_transaction(account_id, transaction_request)
+            .await
+            .map_err(ServiceError::TransactionFailed)?;
+
+        info!(
+            "Submitted transaction {} cancelling {} orders of account {}",
+            transaction_id,
+            orders.len(),
+            account_id
+        );
+
+        // the orders leave the book now, so that they are not reported as trades after the next
+        // sync
+        let block_num = {
+            let mut state = self.state.write().await;
+            for order in orders.iter() {
+                if let Some(note_id) = order.id() {
+                    state.remove(note_id);
+                }
+            }
+            state.block_num()
+        };
+        if let Err(err) = self.store.record_cancels(&orders, block_num) {
+            error!(
+                "Failed to store cancels of transaction {}: {}",
+                transaction_id, err
+            );
+        }
+
+        Ok(Cancellation {
+            orders,
+            transaction_id: Some(transaction_id),
+        })
+    }
+
+    /// Returns true if the SWAPP note of a consumed order was reclaimed by its creator rather than
+    /// filled.
+    ///
+    /// Only the consumers of the notes consumed by the accounts of the client are known, the notes
+    /// consumed by other accounts are taken as filled.
+    async fn is_reclaimed(&self, order: &Order) -> bool {
+        let (Some(note_id), Some(creator)) = (order.id(), order.creator()) else {
+            return false;
+        };
+
+        match self.client.get_note_consumer(note_id).await {
+            Ok(consumer) => consumer == Some(creator),
+            Err(err) => {
+                warn!(
+                    "Failed to get the consumer of note {}: {}",
+                    note_id,
+                    report(&err)
+                );
+                false
+            }
+        }
+    }
+
+    /// Marks the order of a trade as filled, unless its fill was already recorded.
+    fn record_trade(&mut self, trade: Trade) {
+        let Some(note_id) = trade.order.id() else {
+            return;
+        };
+
+        let result = match self.store.get_order(note_id) {
+            Ok(Some(record)) if record.status == OrderStatus::Open => {
+                self.store
+                    .update_status(note_id, OrderStatus::Filled, trade.block_num)
+            }
+            Ok(_) => Ok(()),
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = result {
+            error!("Failed to store trade of order {}: {}", note_id, err);
+        }
+    }
+}
+
+// Order Book Client
+// ================================================================================================
+
+/// Access of the service to the node, through the client: syncs, tracked SWAPP notes and
+/// transactions.
+pub trait OrderBookClient {
+    type Rng: FeltRng;
+
+    /// Syncs with the node and updates the note index of `store`, returns the synced block.
+    async fn sync(&mut self, store: &mut OrderBookStore) -> Result<u32, NoteIndexError>;
+
+    /// Returns the orders of the unspent notes with the given tag, along with the notes which are
+    /// not valid SWAPP notes.
+    async fn get_orders_by_tag(
+        &self,
+        store: &mut OrderBookStore,
+        tag: NoteTag,
+    ) -> Result<(Vec<Order>, Vec<(NoteId, SwappNoteError)>), NoteIndexError>;
+
+    /// Returns the unspent SWAPP notes created by `creator`, only the ones with one of the given
+    /// tags when `tags` is not empty.
+    async fn get_swapp_notes_by_creator(
+        &self,
+        store: &mut OrderBookStore,
+        creator: AccountId,
+        tags: &[NoteTag],
+    ) -> Result<Vec<Note>, NoteIndexError>;
+
+    async fn get_note(&self, note_id: NoteId) -> Result<Note, ServiceError>;
+
+    /// Returns the account which consumed the note, when known.
+    async fn get_note_consumer(&self, note_id: NoteId) -> Result<Option<AccountId>, ClientError>;
+
+    /// Executes and submits a transaction of `account_id`, returns its id.
+    async fn execute_transaction(
+        &mut self,
+        account_id: AccountId,
+        transaction_request: TransactionRequest,
+    ) -> Result<String, ClientError>;
+
+    fn rng(&mut self) -> &mut Self::Rng;
+}
+
+impl<R: FeltRng> OrderBookClient for Client<R> {
+    type Rng = R;
+
+    async fn sync(&mut self, store: &mut OrderBookStore) -> Result<u32, NoteIndexError> {
+        Ok(sync_state(self, store).await?.block_num)
+    }
+
+    async fn get_orders_by_tag(
+        &self,
+        store: &mut OrderBookStore,
+        tag: NoteTag,
+    ) -> Result<(Vec<Order>, Vec<(NoteId, SwappNoteError)>), NoteIndexError> {
+        get_orders_by_tag(self, store, tag).await
+    }
+
+    async fn get_swapp_notes_by_creator(
+        &self,
+        store: &mut OrderBookStore,
+        creator: AccountId,
+        tags: &[NoteTag],
+    ) -> Result<Vec<Note>, NoteIndexError> {
+        get_swapp_notes_by_creator(self, store, creator, tags).await
+    }
+
+    async fn get_note(&self, note_id: NoteId) -> Result<Note, ServiceError> {
+        let record = self.get_input_note(note_id).await?;
+
+        note_from_record(&record).map_err(|e| ServiceError::InvalidNote(note_id, e))
+    }
+
+    async fn get_note_consumer(&self, note_id: NoteId) -> Result<Option<AccountId>, ClientError> {
+        get_note_consumer(self, note_id).await
+    }
+
+    async fn execute_transaction(
+        &mut self,
+        account_id: AccountId,
+        transaction_request: TransactionRequest,
+    ) -> Result<String, ClientError> {
+        let transaction = self
+            .new_transaction(account_id, transaction_request)
+            .await?;
+        let transaction_id = transaction.executed_transaction().id().to_string();
+
+        self.submit_transaction(transaction).await?;
+
+        Ok(transaction_id)
+    }
+
+    fn rng(&mut self) -> &mut R {
+        Client::rng(self)
+    }
+}
+
+// Helpers
+// ================================================================================================
+
+/// Returns the order of the successor SWAPP note of a partial fill.
+fn get_successor_order(note: Option<&Note>) -> Result<Option<Order>, ServiceError> {
+    note.map(|note| Order::try_from(note).map_err(|e| ServiceError::InvalidNote(note.id(), e)))
+        .transpose()
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use miden_client::{
+        accounts::AccountId,
+        assets::{Asset, FungibleAsset},
+        crypto::RpoRandomCoin,
+        notes::{Note, NoteId, NoteTag, NoteType},
+        store::StoreError as ClientStoreError,
+        transactions::TransactionRequest,
+        ClientError, Felt, ZERO,
+    };
+    use miden_order_book::{
+        book::OrderBook,
+        errors::{NoteIndexError, SwappNoteError, TransactionError},
+        note::{create_swapp_note, get_swapp_note_creator},
+        order::{Execution, Order, OrderValidity, SelfTradePrevention},
+        store::{OrderBookStore, OrderStatus},
+    };
+    use tokio::sync::{broadcast, mpsc, oneshot, RwLock};
+    use tokio_stream::StreamExt;
+    use tonic::Request;
+
+    use super::{Command, OrderBookClient, OrderBookService};
+    use crate::{
+        api::OrderBookApi,
+        errors::ServiceError,
+        proto::generated::{
+            order_book_server::OrderBook as _, GetBookRequest, Pair, StreamTradesRequest,
+        },
+        state::BookState,
+    };
+
+    /// Node holding the unspent SWAPP notes in memory, which stands in for the client and the
+    /// node it syncs with. Each sync produces a new block.
+    struct MockNode {
+        block_num: u32,
+        notes: BTreeMap<NoteId, Note>,
+        consumers: BTreeMap<NoteId, AccountId>,
+        /// Accounts which executed the submitted transactions, in order.
+        transactions: Vec<AccountId>,
+        rng: RpoRandomCoin,
+    }
+
+    impl MockNode {
+        fn new() -> Self {
+            Self {
+                block_num: 0,
+                notes: BTreeMap::new(),
+                consumers: BTreeMap::new(),
+                transactions: Vec::new(),
+                rng: RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+            }
+        }
+
+        /// Creates a SWAPP note of `creator` and commits it in the next block.
+        fn add_swapp_note(&mut self, creator: AccountId, offered: Asset, requested: Asset) -> Note {
+            let note = create_swapp_note(
+                creator,
+                offered,
+                requested,
+                NoteType::Public,
+                ZERO,
+                OrderValidity::default(),
+                &mut self.rng,
+            )
+            .unwrap();
+            self.notes.insert(note.id(), note.clone());
+
+            note
+        }
+
+        /// Consumes a note in the next block.
+        fn consume_note(&mut self, note_id: NoteId, consumer: AccountId) {
+            self.notes.remove(&note_id);
+            self.consumers.insert(note_id, consumer);
+        }
+    }
+
+    impl OrderBookClient for MockNode {
+        type Rng = RpoRandomCoin;
+
+        async fn sync(&mut self, _store: &mut OrderBookStore) -> Result<u32, NoteIndexError> {
+            self.block_num += 1;
+            Ok(self.block_num)
+        }
+
+        async fn get_orders_by_tag(
+            &self,
+            _store: &mut OrderBookStore,
+            tag: NoteTag,
+        ) -> Result<(Vec<Order>, Vec<(NoteId, SwappNoteError)>), NoteIndexError> {
+            let mut orders = Vec::new();
+            let mut rejected = Vec::new();
+            for note in self
+                .notes
+                .values()
+                .filter(|note| note.metadata().tag() == tag)
+            {
+                match Order::try_from(note) {
+                    Ok(order) => orders.push(order),
+                    Err(err) => rejected.push((note.id(), err)),
+                }
+            }
+
+            Ok((orders, rejected))
+        }
+
+        async fn get_swapp_notes_by_creator(
+            &self,
+            _store: &mut OrderBookStore,
+            creator: AccountId,
+            tags: &[NoteTag],
+        ) -> Result<Vec<Note>, NoteIndexError> {
+            Ok(self
+                .notes
+                .values()
+                .filter(|note| tags.is_empty() || tags.contains(&note.metadata().tag()))
+                .filter(|note| get_swapp_note_creator(note) == Some(creator))
+                .cloned()
+                .collect())
+        }
+
+        async fn get_note(&self, note_id: NoteId) -> Result<Note, ServiceError> {
+            self.notes
+                .get(&note_id)
+                .cloned()
+                .ok_or(ServiceError::Client(ClientError::StoreError(
+                    ClientStoreError::NoteNotFound(note_id),
+                )))
+        }
+
+        async fn get_note_consumer(
+            &self,
+            note_id: NoteId,
+        ) -> Result<Option<AccountId>, ClientError> {
+            Ok(self.consumers.get(&note_id).copied())
+        }
+
+        async fn execute_transaction(
+            &mut self,
+            account_id: AccountId,
+            _transaction_request: TransactionRequest,
+        ) -> Result<String, ClientError> {
+            self.transactions.push(account_id);
+            Ok(format!("0x{:064x}", self.transactions.len()))
+        }
+
+        fn rng(&mut self) -> &mut RpoRandomCoin {
+            &mut self.rng
+        }
+    }
+
+    fn base_faucet_id() -> AccountId {
+        AccountId::from_hex("0x227bd163275aa1bf").unwrap()
+    }
+
+    fn quote_faucet_id() -> AccountId {
+        AccountId::from_hex("0x2540b08edc3b087d").unwrap()
+    }
+
+    fn maker() -> AccountId {
+        AccountId::from_hex("0x9b6a0ea2ad6e5b3a").unwrap()
+    }
+
+    fn taker() -> AccountId {
+        AccountId::from_hex("0x9b6a0ea2ad6e5c4d").unwrap()
+    }
+
+    fn base(amount: u64) -> Asset {
+        Asset::Fungible(FungibleAsset::new(base_faucet_id(), amount).unwrap())
+    }
+
+    fn quote(amount: u64) -> Asset {
+        Asset::Fungible(FungibleAsset::new(quote_faucet_id(), amount).unwrap())
+    }
+
+    /// Builds a service tracking the pair of the base and quote faucets, along with an API sharing
+    /// its book and trades.
+    fn build_service(
+        node: MockNode,
+        store: OrderBookStore,
+    ) -> (OrderBookService<MockNode>, OrderBookApi) {
+        let book = OrderBook::new(base_faucet_id(), quote_faucet_id()).unwrap();
+        let state = Arc::new(RwLock::new(BookState::default()));
+        let (trades, _) = broadcast::channel(16);
+        let (commands, _) = mpsc::channel(16);
+
+        let api = OrderBookApi::new(state.clone(), trades.clone(), commands);
+        let service = OrderBookService::new(
+            node,
+            store,
+            state,
+            trades,
+            vec![book.bid_tag(), book.ask_tag()],
+        );
+
+        (service, api)
+    }
+
+    async fn book_orders(service: &OrderBookService<MockNode>) -> Vec<Order> {
+        let state = service.state.read().await;
+        match state.book(base_faucet_id(), quote_faucet_id()) {
+            Some(book) => book.asks().chain(book.bids()).copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn order_status(service: &OrderBookService<MockNode>, note_id: NoteId) -> OrderStatus {
+        service.store.get_order(note_id).unwrap().unwrap().status
+    }
+
+    #[tokio::test]
+    async fn filled_orders_are_streamed_as_trades() {
+        let mut node = MockNode::new();
+        let note = node.add_swapp_note(maker(), base(10), quote(20));
+        let order = Order::try_from(&note).unwrap();
+        let (mut service, api) = build_service(node, OrderBookStore::open_in_memory().unwrap());
+
+        let mut trades = api
+            .stream_trades(Request::new(StreamTradesRequest { pair: None }))
+            .await
+            .unwrap()
+            .into_inner();
+
+        // the order is listed once its note is synced
+        service.sync().await;
+        let pair = Pair {
+            base_faucet_id: Some(base_faucet_id().into()),
+            quote_faucet_id: Some(quote_faucet_id().into()),
+        };
+        let response = api
+            .get_book(Request::new(GetBookRequest { pair: Some(pair) }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(response.block_num, 1);
+        assert_eq!(response.asks, vec![order.into()]);
+        assert_eq!(order_status(&service, note.id()), OrderStatus::Open);
+
+        // a taker fills it before the next sync
+        service.client.consume_note(note.id(), taker());
+        service.sync().await;
+
+        let trade = trades.next().await.unwrap().unwrap();
+        assert_eq!(trade.order, Some(order.into()));
+        assert_eq!(trade.block_num, 2);
+        assert!(book_orders(&service).await.is_empty());
+        assert_eq!(order_status(&service, note.id()), OrderStatus::Filled);
+    }
+
+    #[tokio::test]
+    async fn reclaimed_orders_are_not_trades() {
+        let mut node = MockNode::new();
+        let note = node.add_swapp_note(maker(), base(10), quote(20));
+        let (mut service, _api) = build_service(node, OrderBookStore::open_in_memory().unwrap());
+        let mut trades = service.trades.subscribe();
+
+        service.sync().await;
+        assert!(!service.is_reclaimed(&Order::try_from(&note).unwrap()).await);
+
+        // the maker reclaims the note outside of the service
+        service.client.consume_note(note.id(), maker());
+        service.sync().await;
+
+        assert!(trades.try_recv().is_err());
+        assert!(book_orders(&service).await.is_empty());
+        assert_eq!(order_status(&service, note.id()), OrderStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn submitted_orders_fill_the_book() {
+        let mut node = MockNode::new();
+        let note = node.add_swapp_note(maker(), base(10), quote(20));
+        let order = Order::try_from(&note).unwrap();
+        let (mut service, _api) = build_service(node, OrderBookStore::open_in_memory().unwrap());
+        service.sync().await;
+
+        let incoming_order = Order::new(None, quote(20), base(10));
+        let submission = service
+            .submit_order(
+                taker(),
+                incoming_order,
+                Execution::Limit,
+                SelfTradePrevention::Skip,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(submission.plan.fills.len(), 1);
+        assert_eq!(submission.plan.fills[0].order, order);
+        assert_eq!(submission.plan.fills[0].paid, 20);
+        assert_eq!(submission.posted_note_id, None);
+        assert_eq!(service.client.transactions, vec![taker()]);
+
+        let fills = service.store.get_fills(note.id()).unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].fill_amount, 20);
+        assert_eq!(order_status(&service, note.id()), OrderStatus::Filled);
+    }
+
+    #[tokio::test]
+    async fn unmatched_orders_are_posted() {
+        let (mut service, _api) =
+            build_service(MockNode::new(), OrderBookStore::open_in_memory().unwrap());
+        service.sync().await;
+
+        let incoming_order = Order::new(None, quote(20), base(10));
+        let submission = service
+            .submit_order(
+                taker(),
+                incoming_order,
+                Execution::Limit,
+                SelfTradePrevention::Skip,
+            )
+            .await
+            .unwrap();
+
+        assert!(submission.plan.fills.is_empty());
+        assert!(submission.posted_note_id.is_some());
+        assert_eq!(service.client.transactions, vec![taker()]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_orders_leave_the_book() {
+        let mut node = MockNode::new();
+        let note = node.add_swapp_note(maker(), base(10), quote(20));
+        let order = Order::try_from(&note).unwrap();
+        let (mut service, _api) = build_service(node, OrderBookStore::open_in_memory().unwrap());
+        let mut trades = service.trades.subscribe();
+        service.sync().await;
+
+        // only the creator can cancel the order
+        let (reply, response) = oneshot::channel();
+        service
+            .execute(Command::CancelOrder {
+                account_id: taker(),
+                note_id: note.id(),
+                reply,
+            })
+            .await;
+        assert!(matches!(
+            response.await.unwrap(),
+            Err(ServiceError::Transaction(TransactionError::NotCreator(..)))
+        ));
+
+        let (reply, response) = oneshot::channel();
+        service
+            .execute(Command::CancelOrder {
+                account_id: maker(),
+                note_id: note.id(),
+                reply,
+            })
+            .await;
+        let cancellation = response.await.unwrap().unwrap();
+
+        assert_eq!(cancellation.orders, vec![order]);
+        assert!(cancellation.transaction_id.is_some());
+        assert_eq!(service.client.transactions, vec![maker()]);
+        assert!(book_orders(&service).await.is_empty());
+        assert_eq!(order_status(&service, note.id()), OrderStatus::Cancelled);
+
+        // the note is no longer found after the next sync, which is not a trade
+        service.client.consume_note(note.id(), maker());
+        service.sync().await;
+        assert!(trades.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn open_orders_are_restored() {
+        let mut node = MockNode::new();
+        let note = node.add_swapp_note(maker(), base(10), quote(20));
+        let order = Order::try_from(&note).unwrap();
+
+        let mut store = OrderBookStore::open_in_memory().unwrap();
+        store.insert_order(order, None, 3).unwrap();
+
+        let (service, _api) = build_service(MockNode::new(), store);
+        service.restore().await.unwrap();
+
+        assert_eq!(book_orders(&service).await, vec![order]);
+    }
+}
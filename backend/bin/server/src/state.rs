@@ -0,0 +1,137 @@
+use std::collections::{btree_map::Entry, BTreeMap, BTreeSet};
+
+use log::warn;
+use miden_client::{
+    accounts::AccountId,
+    notes::{NoteId, NoteTag},
+};
+use miden_order_book::{
+    book::OrderBook,
+    errors::{report, OrderError},
+    order::Order,
+};
+
+// Book State
+// ================================================================================================
+
+/// In-memory view of the SWAPP notes tracked by the server, refreshed after every sync.
+///
+/// Orders are kept in one [OrderBook] per pair, whose base asset is the faucet with the lowest id.
+#[derive(Debug, Default)]
+pub struct BookState {
+    block_num: u32,
+    books: BTreeMap<(u64, u64), OrderBook>,
+    tags: BTreeMap<u32, BTreeSet<NoteId>>,
+}
+
+impl BookState {
+    pub fn block_num(&self) -> u32 {
+        self.block_num
+    }
+
+    /// Returns the book of the pair, in either direction.
+    pub fn book(&self, faucet_id_1: AccountId, faucet_id_2: AccountId) -> Option<&OrderBook> {
+        self.books.get(&pair_key(faucet_id_1, faucet_id_2))
+    }
+
+    /// Returns the book an order can be matched against.
+    pub fn book_of(&self, order: &Order) -> Option<&OrderBook> {
+        self.book(
+            order.source_asset().faucet_id(),
+            order.target_asset().faucet_id(),
+        )
+    }
+
+    /// Replaces the orders of a tag with the ones found at `block_num`.
+    ///
+    /// Returns the orders which are no longer in the book, whose SWAPP notes were either filled or
+    /// reclaimed by their creators.
+    pub fn update(&mut self, block_num: u32, tag: NoteTag, orders: Vec<Order>) -> Vec<Order> {
+        self.block_num = block_num;
+
+        let current_ids: BTreeSet<NoteId> = orders.iter().filter_map(Order::id).collect();
+        let previous_ids = self
+            .tags
+            .insert(tag.inner(), current_ids.clone())
+            .unwrap_or_default();
+
+        let mut consumed_orders = Vec::new();
+        for id in previous_ids.difference(&current_ids) {
+            if let Some(order) = self.books.values_mut().find_map(|book| book.remove(*id)) {
+                consumed_orders.push(order);
+            }
+        }
+
+        for order in orders {
+            if let Err(err) = self.insert(order) {
+                warn!(
+                    "Failed to add order {:?} to the book: {}",
+                    order.id(),
+                    report(&err)
+                );
+            }
+        }
+
+        consumed_orders
+    }
+
+    /// Removes an order from the book without reporting it as a trade.
+    pub fn remove(&mut self, note_id: NoteId) -> Option<Order> {
+        for ids in self.tags.values_mut() {
+            ids.remove(&note_id);
+        }
+        self.books
+            .values_mut()
+            .find_map(|book| book.remove(note_id))
+    }
+
+    fn insert(&mut self, order: Order) -> Result<(), OrderError> {
+        let source_faucet_id = order.source_asset().faucet_id();
+        let target_faucet_id = order.target_asset().faucet_id();
+        let key = pair_key(source_faucet_id, target_faucet_id);
+
+        let book = match self.books.entry(key) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let (base_faucet_id, quote_faucet_id) =
+                    if u64::from(source_faucet_id) < u64::from(target_faucet_id) {
+                        (source_faucet_id, target_faucet_id)
+                    } else {
+                        (target_faucet_id, source_faucet_id)
+                    };
+                entry.insert(OrderBook::new(base_faucet_id, quote_faucet_id)?)
+            }
+        };
+
+        book.insert(order)
+    }
+}
+
+// Trade
+// ================================================================================================
+
+/// Order whose SWAPP note was filled, detected at `block_num`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trade {
+    pub order: Order,
+    pub block_num: u32,
+}
+
+impl Trade {
+    /// Returns true if the traded order belongs to the given pair, on either side.
+    pub fn is_in_pair(&self, base_faucet_id: AccountId, quote_faucet_id: AccountId) -> bool {
+        pair_key(
+            self.order.source_asset().faucet_id(),
+            self.order.target_asset().faucet_id(),
+        ) == pair_key(base_faucet_id, quote_faucet_id)
+    }
+}
+
+// Helpers
+// ================================================================================================
+
+/// Key of a pair which does not depend on its direction.
+fn pair_key(faucet_id_1: AccountId, faucet_id_2: AccountId) -> (u64, u64) {
+    let (id_1, id_2) = (u64::from(faucet_id_1), u64::from(faucet_id_2));
+    (id_1.min(id_2), id_1.max(id_2))
+}
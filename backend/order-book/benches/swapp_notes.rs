@@ -0,0 +1,69 @@
+//! Bulk creation of SWAPP notes, as done by the setup command, against compiling the SWAPP script
+//! for every note like the note builders used to.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use miden_client::{
+    accounts::AccountId,
+    assets::{Asset, FungibleAsset},
+    crypto::RpoRandomCoin,
+    notes::{NoteScript, NoteType},
+    Felt, ZERO,
+};
+use miden_lib::transaction::TransactionKernel;
+use miden_order_book::{note::create_swapp_note, order::OrderValidity};
+
+const NUM_NOTES: usize = 100;
+
+fn create_swapp_notes(c: &mut Criterion) {
+    let sender = AccountId::from_hex("0x9b6a0ea2ad6e5b3a").unwrap();
+    let offered_faucet_id = AccountId::from_hex("0x227bd163275aa1bf").unwrap();
+    let requested_faucet_id = AccountId::from_hex("0x2540b08edc3b087d").unwrap();
+    let offered_asset = Asset::Fungible(FungibleAsset::new(offered_faucet_id, 10).unwrap());
+    let requested_asset = Asset::Fungible(FungibleAsset::new(requested_faucet_id, 20).unwrap());
+
+    let mut group = c.benchmark_group("swapp_notes");
+
+    group.bench_function("create_100_swapp_notes", |b| {
+        b.iter_batched(
+            || RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+            |mut rng| {
+                for _ in 0..NUM_NOTES {
+                    create_swapp_note(
+                        sender,
+                        offered_asset,
+                        requested_asset,
+                        NoteType::Public,
+                        ZERO,
+                        OrderValidity::default(),
+                        &mut rng,
+                    )
+                    .unwrap();
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    // what creating the notes used to cost on top of building them: two scripts per note
+    group.bench_function("compile_200_scripts", |b| {
+        b.iter(|| {
+            for _ in 0..NUM_NOTES {
+                NoteScript::compile(
+                    include_str!("../src/note/scripts/SWAPP.masm"),
+                    TransactionKernel::assembler(),
+                )
+                .unwrap();
+                NoteScript::compile(
+                    include_str!("../src/note/scripts/P2ID.masm"),
+                    TransactionKernel::assembler(),
+                )
+                .unwrap();
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, create_swapp_notes);
+criterion_main!(benches);
@@ -0,0 +1,495 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use miden_client::{
+    accounts::AccountId,
+    assets::{Asset, FungibleAsset},
+    notes::{build_swap_tag, NoteId, NoteTag, NoteType},
+};
+
+use crate::{errors::OrderError, order::Order, price::Price};
+
+// Side
+// ================================================================================================
+
+/// Side of the book an order rests on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    /// Orders offering the quote asset in exchange for the base asset.
+    Bid,
+    /// Orders offering the base asset in exchange for the quote asset.
+    Ask,
+}
+
+// Price Level
+// ================================================================================================
+
+/// Aggregated view of the orders resting at one price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceLevel {
+    /// Price in quote asset per base asset.
+    pub price: Price,
+    /// Total amount of the base asset offered (asks) or requested (bids) at this price.
+    pub quantity: u64,
+    /// Number of orders resting at this price.
+    pub num_orders: usize,
+}
+
+/// Snapshot of the best price levels of both sides of a book, best price first.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Depth {
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+}
+
+// Order Book
+// ================================================================================================
+
+/// Order book of a trading pair.
+///
+/// Orders are grouped in price levels, in quote asset per base asset, and are filled in
+/// price-time priority: within a level, orders are sorted by the block in which their SWAPP note
+/// was committed and then by note id, so that every client and the server match orders the same
+/// way. Orders are indexed by the id of their SWAPP note, so that inserting and removing an order
+/// is logarithmic in the size of the book.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    base_faucet_id: AccountId,
+    quote_faucet_id: AccountId,
+    bid_tag: NoteTag,
+    ask_tag: NoteTag,
+    bids: BTreeMap<PriceKey, BTreeMap<TimeKey, Order>>,
+    asks: BTreeMap<PriceKey, BTreeMap<TimeKey, Order>>,
+    index: BTreeMap<NoteId, (Side, PriceKey, TimeKey)>,
+}
+
+impl OrderBook {
+    pub fn new(base_faucet_id: AccountId, quote_faucet_id: AccountId) -> Result<Self, OrderError> {
+        let base_asset = Asset::Fungible(
+            FungibleAsset::new(base_faucet_id, 0)
+                .map_err(|_| OrderError::InvalidFaucet(base_faucet_id))?,
+        );
+        let quote_asset = Asset::Fungible(
+            FungibleAsset::new(quote_faucet_id, 0)
+                .map_err(|_| OrderError::InvalidFaucet(quote_faucet_id))?,
+        );
+
+        let bid_tag = build_swap_tag(NoteType::Public, &quote_asset, &base_asset)
+            .map_err(|e| OrderError::InternalError(e.to_string()))?;
+        let ask_tag = build_swap_tag(NoteType::Public, &base_asset, &quote_asset)
+            .map_err(|e| OrderError::InternalError(e.to_string()))?;
+
+        Ok(OrderBook {
+            base_faucet_id,
+            quote_faucet_id,
+            bid_tag,
+            ask_tag,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            index: BTreeMap::new(),
+        })
+    }
+
+    /// Builds the book of a pair from a list of orders.
+    ///
+    /// Orders which do not belong to the pair, or which are not backed by a note, are ignored.
+    pub fn from_orders(
+        base_faucet_id: AccountId,
+        quote_faucet_id: AccountId,
+        orders: impl IntoIterator<Item = Order>,
+    ) -> Result<Self, OrderError> {
+        let mut book = Self::new(base_faucet_id, quote_faucet_id)?;
+        for order in orders {
+            let _ = book.insert(order);
+        }
+
+        Ok(book)
+    }
+
+    pub fn base_faucet_id(&self) -> AccountId {
+        self.base_faucet_id
+    }
+
+    pub fn quote_faucet_id(&self) -> AccountId {
+        self.quote_faucet_id
+    }
+
+    /// Tag of the public SWAPP notes resting on the bid side.
+    pub fn bid_tag(&self) -> NoteTag {
+        self.bid_tag
+    }
+
+    /// Tag of the public SWAPP notes resting on the ask side.
+    pub fn ask_tag(&self) -> NoteTag {
+        self.ask_tag
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains(&self, id: NoteId) -> bool {
+        self.index.contains_key(&id)
+    }
+
+    /// Returns the side an order would rest on, if it belongs to the pair.
+    pub fn side_of(&self, order: &Order) -> Option<Side> {
+        let source_faucet_id = order.source_asset().faucet_id();
+        let target_faucet_id = order.target_asset().faucet_id();
+
+        if source_faucet_id == self.base_faucet_id && target_faucet_id == self.quote_faucet_id {
+            Some(Side::Ask)
+        } else if source_faucet_id == self.quote_faucet_id
+            && target_faucet_id == self.base_faucet_id
+        {
+            Some(Side::Bid)
+        } else {
+            None
+        }
+    }
+
+    /// Adds an order to its price level, after the orders committed in earlier blocks.
+    ///
+    /// Inserting an order which is already in the book replaces it, so that an order first seen
+    /// before its note was committed moves to its final place once the commit block is known.
+    pub fn insert(&mut self, order: Order) -> Result<(), OrderError> {
+        let id = order.id().ok_or(OrderError::MissingId)?;
+        let side = self.side_of(&order).ok_or(OrderError::AssetsNotMatching)?;
+
+        self.remove(id);
+
+        let price = PriceKey::new(side, &order);
+        let time = TimeKey::new(id, &order);
+
+        self.levels_mut(side)
+            .entry(price)
+            .or_default()
+            .insert(time, order);
+        self.index.insert(id, (side, price, time));
+
+        Ok(())
+    }
+
+    /// Removes the order backed by the given note, returning it if it was in the book.
+    pub fn remove(&mut self, id: NoteId) -> Option<Order> {
+        let (side, price, time) = self.index.remove(&id)?;
+
+        let levels = self.levels_mut(side);
+        let level = levels.get_mut(&price)?;
+        let order = level.remove(&time);
+        if level.is_empty() {
+            levels.remove(&price);
+        }
+
+        order
+    }
+
+    pub fn get(&self, id: NoteId) -> Option<&Order> {
+        let (side, price, time) = self.index.get(&id)?;
+        self.levels(*side).get(price)?.get(time)
+    }
+
+    /// Returns the bid with the highest price.
+    pub fn best_bid(&self) -> Option<&Order> {
+        self.bids().next()
+    }
+
+    /// Returns the ask with the lowest price.
+    pub fn best_ask(&self) -> Option<&Order> {
+        self.asks().next()
+    }
+
+    /// Returns the bids from the highest to the lowest price.
+    pub fn bids(&self) -> impl Iterator<Item = &Order> {
+        self.bids.values().flat_map(|level| level.values())
+    }
+
+    /// Returns the asks from the lowest to the highest price.
+    pub fn asks(&self) -> impl Iterator<Item = &Order> {
+        self.asks.values().flat_map(|level| level.values())
+    }
+
+    /// Returns the orders an incoming order can be matched against, best price first.
+    ///
+    /// Returns nothing if the incoming order does not belong to the pair.
+    pub fn resting_orders(&self, incoming_order: &Order) -> Vec<Order> {
+        match self.side_of(incoming_order) {
+            Some(Side::Bid) => self.asks().copied().collect(),
+            Some(Side::Ask) => self.bids().copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the orders offering the asset issued by `faucet_id`, best price first.
+    ///
+    /// Returns nothing if the faucet does not belong to the pair.
+    pub fn orders_offering(&self, faucet_id: AccountId) -> Vec<Order> {
+        if faucet_id == self.base_faucet_id {
+            self.asks().copied().collect()
+        } else if faucet_id == self.quote_faucet_id {
+            self.bids().copied().collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    /// Returns the `num_levels` best price levels of each side.
+    pub fn depth(&self, num_levels: usize) -> Depth {
+        Depth {
+            bids: Self::price_levels(&self.bids, Side::Bid, num_levels),
+            asks: Self::price_levels(&self.asks, Side::Ask, num_levels),
+        }
+    }
+
+    // HELPERS
+    // --------------------------------------------------------------------------------------------
+
+    fn levels(&self, side: Side) -> &BTreeMap<PriceKey, BTreeMap<TimeKey, Order>> {
+        match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        }
+    }
+
+    fn levels_mut(&mut self, side: Side) -> &mut BTreeMap<PriceKey, BTreeMap<TimeKey, Order>> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    fn price_levels(
+        levels: &BTreeMap<PriceKey, BTreeMap<TimeKey, Order>>,
+        side: Side,
+        num_levels: usize,
+    ) -> Vec<PriceLevel> {
+        levels
+            .iter()
+            .take(num_levels)
+            .map(|(price, orders)| PriceLevel {
+                price: price.price(),
+                quantity: orders.values().map(|order| base_amount(side, order)).sum(),
+                num_orders: orders.len(),
+            })
+            .collect()
+    }
+}
+
+// Price Key
+// ================================================================================================
+
+/// Key of a price level, ordered from the best to the worst price of its side: ascending for asks
+/// and descending for bids.
+#[derive(Debug, Clone, Copy)]
+struct PriceKey(Side, Price);
+
+impl PriceKey {
+    fn new(side: Side, order: &Order) -> Self {
+        match side {
+            // asks offer base and request quote, their order price is quote per base
+            Side::Ask => PriceKey(side, order.price()),
+            // bids offer quote and request base, their order price is base per quote
+            Side::Bid => PriceKey(side, order.price().inverse()),
+        }
+    }
+
+    fn price(&self) -> Price {
+        self.1
+    }
+}
+
+impl PartialEq for PriceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.0 {
+            Side::Ask => self.1.cmp(&other.1),
+            Side::Bid => other.1.cmp(&self.1),
+        }
+    }
+}
+
+// Time Key
+// ================================================================================================
+
+/// Key of an order within its price level: orders committed in earlier blocks come first, orders
+/// whose note was not committed yet come last, and ties are broken by note id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct TimeKey {
+    block_num: u32,
+    note_id: NoteId,
+}
+
+impl TimeKey {
+    fn new(note_id: NoteId, order: &Order) -> Self {
+        TimeKey {
+            block_num: order.block_num().unwrap_or(u32::MAX),
+            note_id,
+        }
+    }
+}
+
+// Helpers
+// ================================================================================================
+
+/// Amount of the base asset offered by an ask or requested by a bid.
+fn base_amount(side: Side, order: &Order) -> u64 {
+    match side {
+        Side::Ask => order.source_asset().unwrap_fungible().amount(),
+        Side::Bid => order.target_asset().unwrap_fungible().amount(),
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use miden_client::{
+        accounts::AccountId,
+        assets::{Asset, FungibleAsset},
+        notes::NoteId,
+    };
+
+    use super::{OrderBook, PriceLevel};
+    use crate::{order::Order, price::Price};
+
+    fn faucets() -> (AccountId, AccountId) {
+        (
+            AccountId::from_hex("0x227bd163275aa1bf").unwrap(),
+            AccountId::from_hex("0x2540b08edc3b087d").unwrap(),
+        )
+    }
+
+    fn build_order(
+        note_id: u8,
+        source_faucet_id: AccountId,
+        source_amount: u64,
+        target_faucet_id: AccountId,
+        target_amount: u64,
+    ) -> Order {
+        let note_id = NoteId::try_from_hex(&format!("0x{:064x}", note_id)).unwrap();
+
+        Order::new(
+            Some(note_id),
+            Asset::Fungible(FungibleAsset::new(source_faucet_id, source_amount).unwrap()),
+            Asset::Fungible(FungibleAsset::new(target_faucet_id, target_amount).unwrap()),
+        )
+    }
+
+    #[test]
+    fn book_keeps_price_time_priority() {
+        let (base, quote) = faucets();
+        let mut book = OrderBook::new(base, quote).unwrap();
+
+        // asks at 2, 1 and 1 quote per base
+        let ask1 = build_order(1, base, 10, quote, 20);
+        let ask2 = build_order(2, base, 10, quote, 10).with_block_num(1);
+        let ask3 = build_order(3, base, 5, quote, 5).with_block_num(2);
+        // bids at 0.5 and 0.25 quote per base
+        let bid1 = build_order(4, quote, 5, base, 20);
+        let bid2 = build_order(5, quote, 5, base, 10);
+
+        for order in [ask1, ask2, ask3, bid1, bid2] {
+            book.insert(order).unwrap();
+        }
+
+        assert_eq!(book.len(), 5);
+        assert_eq!(
+            book.asks().copied().collect::<Vec<_>>(),
+            vec![ask2, ask3, ask1]
+        );
+        assert_eq!(book.bids().copied().collect::<Vec<_>>(), vec![bid2, bid1]);
+        assert_eq!(book.best_ask(), Some(&ask2));
+        assert_eq!(book.best_bid(), Some(&bid2));
+
+        // an incoming bid is matched against the asks
+        assert_eq!(book.resting_orders(&bid1), vec![ask2, ask3, ask1]);
+
+        let depth = book.depth(1);
+        assert_eq!(
+            depth.asks,
+            vec![PriceLevel {
+                price: Price::new(10, 10),
+                quantity: 15,
+                num_orders: 2
+            }]
+        );
+        assert_eq!(
+            depth.bids,
+            vec![PriceLevel {
+                price: Price::new(10, 5),
+                quantity: 10,
+                num_orders: 1
+            }]
+        );
+
+        // removing an order keeps the remaining ones in place
+        assert_eq!(book.remove(ask2.id().unwrap()), Some(ask2));
+        assert_eq!(book.remove(ask2.id().unwrap()), None);
+        assert_eq!(book.best_ask(), Some(&ask3));
+        assert_eq!(book.len(), 4);
+    }
+
+    #[test]
+    fn book_sorts_levels_by_commit_block_and_note_id() {
+        let (base, quote) = faucets();
+        let mut book = OrderBook::new(base, quote).unwrap();
+
+        // asks at the same price, inserted in a different order than they were committed
+        let ask1 = build_order(1, base, 10, quote, 10).with_block_num(9);
+        let ask2 = build_order(2, base, 10, quote, 10).with_block_num(7);
+        let ask3 = build_order(3, base, 5, quote, 5).with_block_num(7);
+        let ask4 = build_order(4, base, 5, quote, 5);
+
+        for order in [ask4, ask1, ask3, ask2] {
+            book.insert(order).unwrap();
+        }
+
+        // orders committed in the same block are ordered by note id
+        let (first, second) = if ask2.id() < ask3.id() {
+            (ask2, ask3)
+        } else {
+            (ask3, ask2)
+        };
+        assert_eq!(
+            book.asks().copied().collect::<Vec<_>>(),
+            vec![first, second, ask1, ask4]
+        );
+
+        // once its note is committed, an order takes its place among the others
+        let ask4 = ask4.with_block_num(8);
+        book.insert(ask4).unwrap();
+
+        assert_eq!(book.len(), 4);
+        assert_eq!(
+            book.asks().copied().collect::<Vec<_>>(),
+            vec![first, second, ask4, ask1]
+        );
+    }
+
+    #[test]
+    fn book_rejects_orders_of_other_pairs() {
+        let (base, quote) = faucets();
+        let mut book = OrderBook::new(base, quote).unwrap();
+
+        let order = build_order(1, base, 10, base, 20);
+        assert!(book.insert(order).is_err());
+        assert!(book.is_empty());
+    }
+}
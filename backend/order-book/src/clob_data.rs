@@ -0,0 +1,221 @@
+use std::{fs, path::Path};
+
+use miden_client::{accounts::AccountId, notes::NoteTag};
+use serde::{Deserialize, Serialize};
+
+use crate::{errors::ClobDataError, market::MarketRegistry};
+
+// Clob Data
+// ================================================================================================
+
+/// Faucet created by the setup.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaucetData {
+    pub symbol: String,
+    #[serde(with = "account_id")]
+    pub id: AccountId,
+    pub decimals: u8,
+}
+
+/// Accounts, tags and markets of the order book, written by the setup to
+/// [CLOB_DATA_FILE_PATH](crate::constants::CLOB_DATA_FILE_PATH) so that other commands can use
+/// them as defaults.
+///
+/// Every value is optional, so that a file written by hand or by an older version still loads.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClobData {
+    /// Endpoint of the node the order book was set up on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+    /// Account which created the initial SWAPP notes.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_account_id"
+    )]
+    pub admin: Option<AccountId>,
+    /// Account used by default to execute orders.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "optional_account_id"
+    )]
+    pub user: Option<AccountId>,
+    /// Swap tags to track.
+    #[serde(default, with = "note_tags")]
+    pub tags: Vec<NoteTag>,
+    #[serde(default)]
+    pub faucets: Vec<FaucetData>,
+    #[serde(default)]
+    pub markets: MarketRegistry,
+}
+
+impl ClobData {
+    /// Loads the CLOB data from a TOML file, a missing file is empty CLOB data.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ClobDataError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(path)
+            .map_err(|e| ClobDataError::ReadFailed(path.to_path_buf(), e))?;
+        toml::from_str(&content).map_err(ClobDataError::ParsingError)
+    }
+
+    /// Writes the CLOB data to a TOML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ClobDataError> {
+        let path = path.as_ref();
+        let content = toml::to_string_pretty(self).map_err(ClobDataError::SerializationError)?;
+
+        fs::write(path, content).map_err(|e| ClobDataError::WriteFailed(path.to_path_buf(), e))
+    }
+
+    /// Returns the given account, or the default user when none is given.
+    pub fn user_or_default(&self, user: Option<&str>) -> Result<AccountId, ClobDataError> {
+        match user {
+            Some(user) => AccountId::from_hex(user)
+                .map_err(|e| ClobDataError::InvalidAccountId(user.to_string(), e)),
+            None => self.user.ok_or(ClobDataError::MissingValue("user")),
+        }
+    }
+
+    /// Returns the given tags, or the tags of the CLOB data when none is given.
+    pub fn tags_or_default(&self, tags: &[u32]) -> Result<Vec<NoteTag>, ClobDataError> {
+        if !tags.is_empty() {
+            return Ok(tags.iter().map(|tag| NoteTag::from(*tag)).collect());
+        }
+        if self.tags.is_empty() {
+            return Err(ClobDataError::MissingValue("tags"));
+        }
+
+        Ok(self.tags.clone())
+    }
+}
+
+// Serialization
+// ================================================================================================
+
+/// Serializes account ids as hex strings.
+pub(crate) mod account_id {
+    use miden_client::accounts::AccountId;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(id: &AccountId, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&id.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AccountId, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        AccountId::from_hex(&hex).map_err(D::Error::custom)
+    }
+}
+
+/// Serializes optional account ids as hex strings.
+mod optional_account_id {
+    use miden_client::accounts::AccountId;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        id: &Option<AccountId>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match id {
+            Some(id) => super::account_id::serialize(id, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<AccountId>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| AccountId::from_hex(&hex).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serializes note tags as integers.
+pub(crate) mod note_tag {
+    use miden_client::notes::NoteTag;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(tag: &NoteTag, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(tag.inner())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NoteTag, D::Error> {
+        u32::deserialize(deserializer).map(NoteTag::from)
+    }
+}
+
+/// Serializes lists of note tags as lists of integers.
+mod note_tags {
+    use miden_client::notes::NoteTag;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(tags: &[NoteTag], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(tags.iter().map(|tag| tag.inner()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<NoteTag>, D::Error> {
+        let tags = Vec::<u32>::deserialize(deserializer)?;
+        Ok(tags.into_iter().map(NoteTag::from).collect())
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use miden_client::{accounts::AccountId, notes::NoteTag};
+
+    use super::{ClobData, FaucetData};
+    use crate::{errors::ClobDataError, market::Market};
+
+    #[test]
+    fn clob_data_round_trips_through_toml() {
+        let faucet1 = AccountId::from_hex("0x227bd163275aa1bf").unwrap();
+        let faucet2 = AccountId::from_hex("0x2540b08edc3b087d").unwrap();
+        let market = Market::new("ASSETA", faucet1, 10, "ASSETB", faucet2, 10, 1, 1).unwrap();
+
+        // any valid account id will do for the user
+        let mut clob_data = ClobData {
+            endpoint: Some("http://localhost:57291".to_string()),
+            admin: None,
+            user: Some(faucet2),
+            tags: vec![market.ask_tag(), market.bid_tag()],
+            faucets: vec![FaucetData {
+                symbol: "ASSETA".to_string(),
+                id: faucet1,
+                decimals: 10,
+            }],
+            ..Default::default()
+        };
+        clob_data.markets.add(market.clone());
+
+        let content = toml::to_string_pretty(&clob_data).unwrap();
+        let loaded: ClobData = toml::from_str(&content).unwrap();
+
+        assert_eq!(loaded, clob_data);
+        assert_eq!(loaded.markets.get("ASSETA/ASSETB"), Ok(&market));
+        assert_eq!(
+            loaded.user_or_default(None).unwrap(),
+            clob_data.user.unwrap()
+        );
+        assert_eq!(
+            loaded.tags_or_default(&[7]).unwrap(),
+            vec![NoteTag::from(7)]
+        );
+
+        // an empty file is valid, but has no defaults
+        let empty: ClobData = toml::from_str("").unwrap();
+        assert!(matches!(
+            empty.user_or_default(None),
+            Err(ClobDataError::MissingValue("user"))
+        ));
+    }
+}
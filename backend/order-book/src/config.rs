@@ -0,0 +1,255 @@
+use std::{fmt, fs, path::Path, str::FromStr};
+
+use miden_client::config::{Endpoint, RpcConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::{constants::DB_FILE_PATH, errors::ConfigError};
+
+// Network
+// ================================================================================================
+
+/// Well known networks, used to pick the default endpoint and store of the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    Localhost,
+    Devnet,
+    Testnet,
+}
+
+impl Network {
+    pub fn endpoint(&self) -> &'static str {
+        match self {
+            Network::Localhost => "http://localhost:57291",
+            Network::Devnet => "https://rpc.devnet.miden.io:443",
+            Network::Testnet => "https://rpc.testnet.miden.io:443",
+        }
+    }
+
+    /// Client store of the network, so that accounts and notes of different networks never mix.
+    pub fn store_path(&self) -> String {
+        match self {
+            Network::Localhost => DB_FILE_PATH.to_string(),
+            Network::Devnet => "store.devnet.sqlite3".to_string(),
+            Network::Testnet => "store.testnet.sqlite3".to_string(),
+        }
+    }
+}
+
+impl FromStr for Network {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "localhost" => Ok(Network::Localhost),
+            "devnet" => Ok(Network::Devnet),
+            "testnet" => Ok(Network::Testnet),
+            _ => Err(ConfigError::UnknownNetwork(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Localhost => write!(f, "localhost"),
+            Network::Devnet => write!(f, "devnet"),
+            Network::Testnet => write!(f, "testnet"),
+        }
+    }
+}
+
+// Client Config
+// ================================================================================================
+
+/// Prover used to prove the transactions of the client.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ProverConfig {
+    /// Proves transactions in process.
+    #[default]
+    Local,
+    /// Delegates proving to a remote prover.
+    Remote { endpoint: String },
+}
+
+/// Configuration of the Miden client, read from
+/// [CLIENT_CONFIG_FILE_PATH](crate::constants::CLIENT_CONFIG_FILE_PATH) and overridden by
+/// environment variables and command line flags (see [ClientOverrides]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// Endpoint of the node RPC, `protocol://host:port`.
+    pub endpoint: String,
+    pub timeout_ms: u64,
+    /// Path of the client store.
+    pub store_path: String,
+    pub debug_mode: bool,
+    pub prover: ProverConfig,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self::for_network(Network::Localhost)
+    }
+}
+
+impl ClientConfig {
+    pub fn for_network(network: Network) -> Self {
+        Self {
+            endpoint: network.endpoint().to_string(),
+            timeout_ms: 10000,
+            store_path: network.store_path(),
+            debug_mode: true,
+            prover: ProverConfig::Local,
+        }
+    }
+
+    /// Loads the client config from a TOML file, a missing file is the default config.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content =
+            fs::read_to_string(path).map_err(|e| ConfigError::ReadFailed(path.to_path_buf(), e))?;
+        toml::from_str(&content).map_err(ConfigError::ParsingError)
+    }
+
+    pub fn rpc_config(&self) -> Result<RpcConfig, ConfigError> {
+        Ok(RpcConfig {
+            endpoint: parse_endpoint(&self.endpoint)?,
+            timeout_ms: self.timeout_ms,
+        })
+    }
+}
+
+/// Parses an endpoint of the form `protocol://host:port`.
+pub fn parse_endpoint(endpoint: &str) -> Result<Endpoint, ConfigError> {
+    let invalid = || ConfigError::InvalidEndpoint(endpoint.to_string());
+
+    let (protocol, address) = endpoint.split_once("://").ok_or_else(invalid)?;
+    let (host, port) = address
+        .trim_end_matches('/')
+        .rsplit_once(':')
+        .ok_or_else(invalid)?;
+    let port = port.parse::<u16>().map_err(|_| invalid())?;
+    if protocol.is_empty() || host.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(Endpoint::new(protocol.to_string(), host.to_string(), port))
+}
+
+// Client Overrides
+// ================================================================================================
+
+/// Settings which take precedence over the client config file, set by the binaries from their
+/// flags and the environment.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientOverrides {
+    /// Network to connect to, sets the default endpoint and store.
+    pub network: Option<Network>,
+    pub rpc_endpoint: Option<String>,
+    pub rpc_timeout_ms: Option<u64>,
+    pub store_path: Option<String>,
+    pub debug_mode: Option<bool>,
+    /// Remote prover endpoint, transactions are proven locally when not set.
+    pub prover_endpoint: Option<String>,
+}
+
+impl ClientOverrides {
+    /// Returns the client config: the config file at `path`, then the network defaults, then the
+    /// overrides.
+    pub fn config(&self, path: impl AsRef<Path>) -> Result<ClientConfig, ConfigError> {
+        let mut config = ClientConfig::load(path)?;
+
+        if let Some(network) = self.network {
+            config.endpoint = network.endpoint().to_string();
+            config.store_path = network.store_path();
+        }
+        if let Some(endpoint) = &self.rpc_endpoint {
+            config.endpoint = endpoint.clone();
+        }
+        if let Some(timeout_ms) = self.rpc_timeout_ms {
+            config.timeout_ms = timeout_ms;
+        }
+        if let Some(store_path) = &self.store_path {
+            config.store_path = store_path.clone();
+        }
+        if let Some(debug_mode) = self.debug_mode {
+            config.debug_mode = debug_mode;
+        }
+        if let Some(endpoint) = &self.prover_endpoint {
+            config.prover = ProverConfig::Remote {
+                endpoint: endpoint.clone(),
+            };
+        }
+
+        // fail early rather than on the first request to the node
+        parse_endpoint(&config.endpoint)?;
+        Ok(config)
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_endpoint, ClientConfig, Network, ProverConfig};
+    use crate::errors::ConfigError;
+
+    #[test]
+    fn client_config_reads_partial_files() {
+        let config: ClientConfig = toml::from_str(
+            r#"
+            endpoint = "https://rpc.testnet.miden.io:443"
+            store_path = "testnet.sqlite3"
+
+            [prover]
+            kind = "remote"
+            endpoint = "http://localhost:50051"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.timeout_ms, ClientConfig::default().timeout_ms);
+        assert_eq!(config.store_path, "testnet.sqlite3");
+        assert_eq!(
+            config.prover,
+            ProverConfig::Remote {
+                endpoint: "http://localhost:50051".to_string()
+            }
+        );
+        assert_eq!(
+            config.rpc_config().unwrap().endpoint,
+            parse_endpoint(Network::Testnet.endpoint()).unwrap()
+        );
+    }
+
+    #[test]
+    fn networks_parse_from_their_names() {
+        for network in [Network::Localhost, Network::Devnet, Network::Testnet] {
+            assert_eq!(network.to_string().parse::<Network>().unwrap(), network);
+        }
+        assert!(matches!(
+            "mainnet".parse::<Network>(),
+            Err(ConfigError::UnknownNetwork(network)) if network == "mainnet"
+        ));
+    }
+
+    #[test]
+    fn endpoints_need_protocol_host_and_port() {
+        let endpoint = parse_endpoint("http://localhost:57291").unwrap();
+        assert_eq!(endpoint.to_string(), "http://localhost:57291");
+
+        for invalid in ["localhost:57291", "http://localhost", "http://:57291"] {
+            assert!(matches!(
+                parse_endpoint(invalid),
+                Err(ConfigError::InvalidEndpoint(endpoint)) if endpoint == invalid
+            ));
+        }
+    }
+}
@@ -1,3 +1,5 @@
 pub const DB_FILE_PATH: &str = "store.sqlite3";
+pub const ORDER_BOOK_DB_FILE_PATH: &str = "order_book.sqlite3";
 pub const CLOB_DATA_FILE_PATH: &str = "clob_data.toml";
 pub const ACCOUNTS_DIR: &str = "accounts";
+pub const CLIENT_CONFIG_FILE_PATH: &str = "miden-client.toml";
@@ -1,12 +1,33 @@
-use crate::order::Order;
-use std::fmt;
+use crate::{
+    note::SWAPP_NUM_INPUTS,
+    order::{Order, OrderValidity, SlippageGuard},
+    price::Price,
+};
+use miden_client::{
+    accounts::AccountId,
+    notes::{NoteError, NoteId, NoteTag},
+    store::StoreError as ClientStoreError,
+    transactions::TransactionRequestError,
+    ClientError,
+};
+use miden_objects::{AccountError, AssetError, Digest};
+use std::{error::Error, fmt, io, path::PathBuf};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum OrderError {
     AssetsNotMatching,
-    PriceTooHigh(u64, u64),
+    PriceTooHigh(Price, Price),
     FailedFill(Order),
     MissingId,
+    InvalidFaucet(AccountId),
+    InactiveOrder(OrderValidity, u32),
+    NotFullyFilled(u64, u64),
+    WouldCross(usize),
+    InsufficientLiquidity(u64, u64),
+    ZeroAmount,
+    SlippageExceeded(Price, SlippageGuard),
+    SelfTrade(Order),
+    InvalidFillAmount(u64, u64),
     InternalError(String),
 }
 
@@ -21,7 +42,488 @@ impl fmt::Display for OrderError {
             ),
             OrderError::FailedFill(order) => write!(f, "Failed to fill order: {:?}", order),
             OrderError::MissingId => write!(f, "Missing ID"),
+            OrderError::InvalidFaucet(id) => write!(f, "Account {} is not a fungible faucet", id),
+            OrderError::InactiveOrder(validity, block_num) => write!(
+                f,
+                "Order can not be filled at block {}, it is valid from block {:?} to block {:?}",
+                block_num, validity.good_after_block, validity.expiry_block
+            ),
+            OrderError::NotFullyFilled(filled_amount, source_amount) => write!(
+                f,
+                "Fill-or-kill order killed, only {} of {} could be filled",
+                filled_amount, source_amount
+            ),
+            OrderError::WouldCross(num_orders) => write!(
+                f,
+                "Post-only order would take liquidity from {} resting orders",
+                num_orders
+            ),
+            OrderError::InsufficientLiquidity(filled_amount, amount) => write!(
+                f,
+                "The book can only fill {} of the {} of the market order",
+                filled_amount, amount
+            ),
+            OrderError::ZeroAmount => write!(f, "Market order amount must be positive"),
+            OrderError::SelfTrade(order) => write!(
+                f,
+                "Incoming order would trade against order {:?} of the same account",
+                order.id()
+            ),
+            OrderError::SlippageExceeded(average_price, guard) => match guard {
+                SlippageGuard::MaxAveragePrice(max_price) => write!(
+                    f,
+                    "Average price {} is above the maximum price {}",
+                    average_price, max_price
+                ),
+                SlippageGuard::MaxSlippageBps(bps) => write!(
+                    f,
+                    "Average price {} is more than {} basis points above the best price",
+                    average_price, bps
+                ),
+            },
+            OrderError::InvalidFillAmount(fill_amount, requested_amount) => write!(
+                f,
+                "Fill amount {} can not be exchanged against the requested amount {}",
+                fill_amount, requested_amount
+            ),
             OrderError::InternalError(msg) => write!(f, "Internal error: {}", msg),
         }
     }
 }
+
+impl Error for OrderError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SwappInputsError {
+    WrongNumberOfInputs(usize),
+    InvalidAccountId(String),
+    InvalidAsset(String),
+    InvalidTag(u64),
+    InvalidExecutionHint(u64),
+    InvalidBlockNumber(u64),
+}
+
+impl fmt::Display for SwappInputsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwappInputsError::WrongNumberOfInputs(num_inputs) => write!(
+                f,
+                "Expected {} inputs, got {}",
+                SWAPP_NUM_INPUTS, num_inputs
+            ),
+            SwappInputsError::InvalidAccountId(msg) => write!(f, "Invalid account id: {}", msg),
+            SwappInputsError::InvalidAsset(msg) => write!(f, "Invalid requested asset: {}", msg),
+            SwappInputsError::InvalidTag(tag) => write!(f, "Invalid note tag {}", tag),
+            SwappInputsError::InvalidExecutionHint(hint) => {
+                write!(f, "Invalid execution hint {}", hint)
+            }
+            SwappInputsError::InvalidBlockNumber(block_num) => {
+                write!(f, "Invalid block number {}", block_num)
+            }
+        }
+    }
+}
+
+impl Error for SwappInputsError {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum SwappNoteError {
+    NotSwappScript(Digest),
+    ScriptHashMismatch(Digest),
+    InvalidInputs(SwappInputsError),
+    WrongNumberOfAssets(usize),
+    NonFungibleAsset,
+    MissingMetadata,
+    TagMismatch(NoteTag),
+}
+
+impl fmt::Display for SwappNoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SwappNoteError::NotSwappScript(root) => {
+                write!(f, "Script {} is not the SWAPP script", root)
+            }
+            SwappNoteError::ScriptHashMismatch(hash) => write!(
+                f,
+                "Inputs hold script hash {} instead of the SWAPP script hash",
+                hash
+            ),
+            SwappNoteError::InvalidInputs(_) => write!(f, "Invalid SWAPP note inputs"),
+            SwappNoteError::WrongNumberOfAssets(num_assets) => {
+                write!(f, "Expected a single asset, got {}", num_assets)
+            }
+            SwappNoteError::NonFungibleAsset => write!(f, "Offered asset is not fungible"),
+            SwappNoteError::MissingMetadata => write!(f, "Note has no metadata"),
+            SwappNoteError::TagMismatch(tag) => {
+                write!(f, "Tag {} does not match the assets of the note", tag)
+            }
+        }
+    }
+}
+
+impl Error for SwappNoteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SwappNoteError::InvalidInputs(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<SwappInputsError> for SwappNoteError {
+    fn from(err: SwappInputsError) -> Self {
+        SwappNoteError::InvalidInputs(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum TransactionError {
+    InvalidNote(NoteError),
+    InvalidAsset(AssetError),
+    InvalidRequest(TransactionRequestError),
+    InvalidNoteInputs(SwappInputsError),
+    InvalidSwappNote(SwappNoteError),
+    InvalidDistribution(u64, usize),
+    MissingId,
+    FillExceedsRequested(u64, u64),
+    FillExceedsOffered(u64, u64),
+    UnfillableNote(NoteId),
+    UnknownPaybackScript(NoteId),
+    NothingToReclaim,
+    NotCreator(NoteId, AccountId),
+}
+
+impl fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionError::InvalidNote(_) => write!(f, "Failed to build note"),
+            TransactionError::InvalidAsset(_) => write!(f, "Failed to build asset"),
+            TransactionError::InvalidRequest(_) => write!(f, "Failed to build transaction request"),
+            TransactionError::InvalidNoteInputs(_) => write!(f, "Invalid SWAPP note inputs"),
+            TransactionError::InvalidSwappNote(_) => write!(f, "Invalid SWAPP note"),
+            TransactionError::InvalidDistribution(total, num_notes) => write!(
+                f,
+                "Can not split {} into {} non-zero amounts",
+                total, num_notes
+            ),
+            TransactionError::MissingId => write!(f, "Order has no note to consume"),
+            TransactionError::FillExceedsRequested(fill_amount, requested_amount) => write!(
+                f,
+                "Fill amount {} exceeds the requested amount {}",
+                fill_amount, requested_amount
+            ),
+            TransactionError::FillExceedsOffered(amount_out, offered_amount) => write!(
+                f,
+                "Fill pays out {} which exceeds the offered amount {}",
+                amount_out, offered_amount
+            ),
+            TransactionError::UnfillableNote(note_id) => {
+                write!(
+                    f,
+                    "SWAPP note {} has a zero amount and can not be filled",
+                    note_id
+                )
+            }
+            TransactionError::UnknownPaybackScript(note_id) => write!(
+                f,
+                "SWAPP note {} does not pay back with P2ID notes",
+                note_id
+            ),
+            TransactionError::NothingToReclaim => write!(f, "No SWAPP note to reclaim"),
+            TransactionError::NotCreator(note_id, account_id) => write!(
+                f,
+                "Account {} did not create the order of note {}",
+                account_id, note_id
+            ),
+        }
+    }
+}
+
+impl Error for TransactionError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TransactionError::InvalidNote(err) => Some(err),
+            TransactionError::InvalidAsset(err) => Some(err),
+            TransactionError::InvalidRequest(err) => Some(err),
+            TransactionError::InvalidNoteInputs(err) => Some(err),
+            TransactionError::InvalidSwappNote(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<NoteError> for TransactionError {
+    fn from(err: NoteError) -> Self {
+        TransactionError::InvalidNote(err)
+    }
+}
+
+impl From<AssetError> for TransactionError {
+    fn from(err: AssetError) -> Self {
+        TransactionError::InvalidAsset(err)
+    }
+}
+
+impl From<TransactionRequestError> for TransactionError {
+    fn from(err: TransactionRequestError) -> Self {
+        TransactionError::InvalidRequest(err)
+    }
+}
+
+impl From<SwappInputsError> for TransactionError {
+    fn from(err: SwappInputsError) -> Self {
+        TransactionError::InvalidNoteInputs(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    DatabaseError(rusqlite::Error),
+    ParsingError(String),
+    OrderNotFound(NoteId),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::DatabaseError(_) => write!(f, "Database error"),
+            StoreError::ParsingError(msg) => write!(f, "Failed to parse stored value: {}", msg),
+            StoreError::OrderNotFound(id) => write!(f, "Order {} not found", id),
+        }
+    }
+}
+
+impl Error for StoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StoreError::DatabaseError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for StoreError {
+    fn from(err: rusqlite::Error) -> Self {
+        StoreError::DatabaseError(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum NoteIndexError {
+    ClientError(ClientError),
+    StoreError(StoreError),
+}
+
+impl fmt::Display for NoteIndexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NoteIndexError::ClientError(_) => write!(f, "Failed to read notes from the client"),
+            NoteIndexError::StoreError(_) => write!(f, "Failed to update the note index"),
+        }
+    }
+}
+
+impl Error for NoteIndexError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            NoteIndexError::ClientError(err) => Some(err),
+            NoteIndexError::StoreError(err) => Some(err),
+        }
+    }
+}
+
+impl From<ClientError> for NoteIndexError {
+    fn from(err: ClientError) -> Self {
+        NoteIndexError::ClientError(err)
+    }
+}
+
+impl From<StoreError> for NoteIndexError {
+    fn from(err: StoreError) -> Self {
+        NoteIndexError::StoreError(err)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum MarketError {
+    NoMarkets,
+    UnknownMarket(String),
+    InvalidMarket(String, String),
+    MalformedPrice(String),
+    InvalidPrice(String, String),
+    InvalidQuantity(u64, u64),
+    InvalidAmount(String),
+}
+
+impl fmt::Display for MarketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MarketError::NoMarkets => write!(f, "No market is registered, run setup first"),
+            MarketError::UnknownMarket(symbol) => write!(f, "Unknown market {}", symbol),
+            MarketError::InvalidMarket(symbol, msg) => {
+                write!(f, "Invalid market {}: {}", symbol, msg)
+            }
+            MarketError::MalformedPrice(price) => {
+                write!(f, "Price {} is not a decimal number", price)
+            }
+            MarketError::InvalidPrice(price, tick_size) => write!(
+                f,
+                "Price {} is not a positive multiple of the tick size {}",
+                price, tick_size
+            ),
+            MarketError::InvalidQuantity(quantity, lot_size) => write!(
+                f,
+                "Quantity {} is not a positive multiple of the lot size {}",
+                quantity, lot_size
+            ),
+            MarketError::InvalidAmount(msg) => write!(f, "Invalid order amount: {}", msg),
+        }
+    }
+}
+
+impl Error for MarketError {}
+
+#[derive(Debug)]
+pub enum ClobDataError {
+    ReadFailed(PathBuf, io::Error),
+    WriteFailed(PathBuf, io::Error),
+    ParsingError(toml::de::Error),
+    SerializationError(toml::ser::Error),
+    InvalidAccountId(String, AccountError),
+    MissingValue(&'static str),
+    MarketError(MarketError),
+}
+
+impl fmt::Display for ClobDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClobDataError::ReadFailed(path, _) => write!(f, "Failed to read {:?}", path),
+            ClobDataError::WriteFailed(path, _) => write!(f, "Failed to write {:?}", path),
+            ClobDataError::ParsingError(_) => write!(f, "Failed to parse CLOB data"),
+            ClobDataError::SerializationError(_) => write!(f, "Failed to serialize CLOB data"),
+            ClobDataError::InvalidAccountId(account_id, _) => {
+                write!(f, "Invalid account id {}", account_id)
+            }
+            ClobDataError::MissingValue(name) => {
+                write!(
+                    f,
+                    "No {} in the CLOB data, run setup or pass it explicitly",
+                    name
+                )
+            }
+            ClobDataError::MarketError(_) => write!(f, "Invalid market in the CLOB data"),
+        }
+    }
+}
+
+impl Error for ClobDataError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClobDataError::ReadFailed(_, err) => Some(err),
+            ClobDataError::WriteFailed(_, err) => Some(err),
+            ClobDataError::ParsingError(err) => Some(err),
+            ClobDataError::SerializationError(err) => Some(err),
+            ClobDataError::InvalidAccountId(_, err) => Some(err),
+            ClobDataError::MarketError(err) => Some(err),
+            ClobDataError::MissingValue(_) => None,
+        }
+    }
+}
+
+impl From<MarketError> for ClobDataError {
+    fn from(err: MarketError) -> Self {
+        ClobDataError::MarketError(err)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    ReadFailed(PathBuf, io::Error),
+    ParsingError(toml::de::Error),
+    InvalidEndpoint(String),
+    UnknownNetwork(String),
+    ClientStoreError(ClientStoreError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ReadFailed(path, _) => write!(f, "Failed to read {:?}", path),
+            ConfigError::ParsingError(_) => write!(f, "Failed to parse client config"),
+            ConfigError::InvalidEndpoint(endpoint) => {
+                write!(
+                    f,
+                    "Invalid endpoint {}, expected protocol://host:port",
+                    endpoint
+                )
+            }
+            ConfigError::UnknownNetwork(network) => write!(
+                f,
+                "Unknown network {}, expected localhost, devnet or testnet",
+                network
+            ),
+            ConfigError::ClientStoreError(_) => write!(f, "Failed to open the client store"),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ConfigError::ReadFailed(_, err) => Some(err),
+            ConfigError::ParsingError(err) => Some(err),
+            ConfigError::ClientStoreError(err) => Some(err),
+            ConfigError::InvalidEndpoint(_) | ConfigError::UnknownNetwork(_) => None,
+        }
+    }
+}
+
+impl From<ClientStoreError> for ConfigError {
+    fn from(err: ClientStoreError) -> Self {
+        ConfigError::ClientStoreError(err)
+    }
+}
+
+/// Formats an error followed by the chain of its sources, separated by colons.
+pub fn report(err: &dyn Error) -> String {
+    let mut report = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        report.push_str(&format!(": {}", err));
+        source = err.source();
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_chains_sources() {
+        let err = SwappNoteError::InvalidInputs(SwappInputsError::WrongNumberOfInputs(3));
+        assert_eq!(
+            report(&err),
+            format!(
+                "Invalid SWAPP note inputs: {}",
+                SwappInputsError::WrongNumberOfInputs(3)
+            )
+        );
+
+        let err = ClobDataError::MarketError(MarketError::UnknownMarket("A/B".to_string()));
+        assert_eq!(
+            report(&err),
+            "Invalid market in the CLOB data: Unknown market A/B"
+        );
+
+        let err = ConfigError::ReadFailed(
+            PathBuf::from("client.toml"),
+            io::Error::new(io::ErrorKind::PermissionDenied, "permission denied"),
+        );
+        assert_eq!(
+            report(&err),
+            "Failed to read \"client.toml\": permission denied"
+        );
+    }
+}
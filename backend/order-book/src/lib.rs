@@ -1,5 +1,11 @@
+pub mod book;
+pub mod clob_data;
+pub mod config;
 pub mod constants;
 pub mod errors;
+pub mod market;
 pub mod note;
 pub mod order;
+pub mod price;
+pub mod store;
 pub mod utils;
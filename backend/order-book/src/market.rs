@@ -0,0 +1,604 @@
+use miden_client::{
+    accounts::AccountId,
+    assets::{Asset, FungibleAsset},
+    notes::NoteTag,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    book::{OrderBook, Side},
+    clob_data::{account_id, note_tag},
+    errors::MarketError,
+    order::{MarketAmount, MarketOrder, Order, SlippageGuard},
+    price::Price,
+};
+
+// Market
+// ================================================================================================
+
+/// Trading pair listed in the market registry.
+///
+/// Prices are expressed in quote asset per base asset: asks offer the base asset and bids offer
+/// the quote asset. Prices are given and shown in whole assets, such as `0.5` quote asset per base
+/// asset, while quantities are in the smallest unit of the base asset.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Market {
+    base_symbol: String,
+    #[serde(with = "account_id")]
+    base_faucet_id: AccountId,
+    base_decimals: u8,
+    quote_symbol: String,
+    #[serde(with = "account_id")]
+    quote_faucet_id: AccountId,
+    quote_decimals: u8,
+    /// Smallest price increment, in smallest units of the quote asset per whole base asset.
+    tick_size: u64,
+    /// Smallest quantity increment, in base asset.
+    lot_size: u64,
+    #[serde(with = "note_tag")]
+    bid_tag: NoteTag,
+    #[serde(with = "note_tag")]
+    ask_tag: NoteTag,
+}
+
+impl Market {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        base_symbol: &str,
+        base_faucet_id: AccountId,
+        base_decimals: u8,
+        quote_symbol: &str,
+        quote_faucet_id: AccountId,
+        quote_decimals: u8,
+        tick_size: u64,
+        lot_size: u64,
+    ) -> Result<Self, MarketError> {
+        let symbol = format!("{}/{}", base_symbol, quote_symbol);
+        if base_symbol.is_empty() || quote_symbol.is_empty() || base_symbol == quote_symbol {
+            return Err(MarketError::InvalidMarket(
+                symbol,
+                "Base and quote symbols must be set and differ".to_string(),
+            ));
+        }
+        if tick_size == 0 || lot_size == 0 {
+            return Err(MarketError::InvalidMarket(
+                symbol,
+                "Tick and lot sizes must not be zero".to_string(),
+            ));
+        }
+        if base_decimals > MAX_DECIMALS || quote_decimals > MAX_DECIMALS {
+            return Err(MarketError::InvalidMarket(
+                symbol,
+                format!("Decimals must be at most {}", MAX_DECIMALS),
+            ));
+        }
+
+        // the book checks both faucets and computes the tags of the pair
+        let book = OrderBook::new(base_faucet_id, quote_faucet_id)
+            .map_err(|e| MarketError::InvalidMarket(symbol, e.to_string()))?;
+
+        Ok(Market {
+            base_symbol: base_symbol.to_string(),
+            base_faucet_id,
+            base_decimals,
+            quote_symbol: quote_symbol.to_string(),
+            quote_faucet_id,
+            quote_decimals,
+            tick_size,
+            lot_size,
+            bid_tag: book.bid_tag(),
+            ask_tag: book.ask_tag(),
+        })
+    }
+
+    /// Returns the symbol of the market, `BASE/QUOTE`.
+    pub fn symbol(&self) -> String {
+        format!("{}/{}", self.base_symbol, self.quote_symbol)
+    }
+
+    pub fn base_symbol(&self) -> &str {
+        &self.base_symbol
+    }
+
+    pub fn base_faucet_id(&self) -> AccountId {
+        self.base_faucet_id
+    }
+
+    pub fn base_decimals(&self) -> u8 {
+        self.base_decimals
+    }
+
+    pub fn quote_symbol(&self) -> &str {
+        &self.quote_symbol
+    }
+
+    pub fn quote_faucet_id(&self) -> AccountId {
+        self.quote_faucet_id
+    }
+
+    pub fn quote_decimals(&self) -> u8 {
+        self.quote_decimals
+    }
+
+    pub fn tick_size(&self) -> u64 {
+        self.tick_size
+    }
+
+    pub fn lot_size(&self) -> u64 {
+        self.lot_size
+    }
+
+    /// Tag of the public SWAPP notes offering the quote asset.
+    pub fn bid_tag(&self) -> NoteTag {
+        self.bid_tag
+    }
+
+    /// Tag of the public SWAPP notes offering the base asset.
+    pub fn ask_tag(&self) -> NoteTag {
+        self.ask_tag
+    }
+
+    /// Returns the tick size in quote asset per base asset, such as `0.01`.
+    pub fn display_tick_size(&self) -> String {
+        format_decimal(self.tick_size as u128, self.quote_decimals)
+    }
+
+    /// Parses a price in quote asset per base asset, a decimal such as `0.5`, into smallest units
+    /// of the quote asset per whole base asset.
+    ///
+    /// Prices with more decimals than the quote asset are rejected, as they can not be a multiple
+    /// of the tick size.
+    pub fn parse_price(&self, price: &str) -> Result<u64, MarketError> {
+        let malformed = || MarketError::MalformedPrice(price.to_string());
+        let (integer, fraction) = price.split_once('.').unwrap_or((price, ""));
+        if integer.is_empty() && fraction.is_empty() {
+            return Err(malformed());
+        }
+        if !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return Err(malformed());
+        }
+
+        let decimals = self.quote_decimals as usize;
+        if fraction.len() > decimals && fraction[decimals..].chars().any(|c| c != '0') {
+            return Err(MarketError::InvalidPrice(
+                price.to_string(),
+                self.display_tick_size(),
+            ));
+        }
+        let fraction = &fraction[..fraction.len().min(decimals)];
+
+        format!("{}{:0<width$}", integer, fraction, width = decimals)
+            .parse()
+            .map_err(|_| malformed())
+    }
+
+    /// Returns a price of the market in smallest units of each asset, as returned by
+    /// [Market::price_of], in quote asset per base asset. The price is truncated to the decimals
+    /// of the quote asset.
+    pub fn format_price(&self, price: Price) -> String {
+        if price.offered() == 0 {
+            return "inf".to_string();
+        }
+
+        let units =
+            price.requested() as u128 * unit(self.base_decimals) as u128 / price.offered() as u128;
+        format_decimal(units, self.quote_decimals)
+    }
+
+    /// Builds a limit order for `quantity` base asset at `price` quote asset per base asset, see
+    /// [Market::parse_price].
+    ///
+    /// Bids offer `price * quantity` of the quote asset for `quantity` of the base asset, asks
+    /// offer `quantity` of the base asset for `price * quantity` of the quote asset. The price must
+    /// be a multiple of the tick size, the quantity a multiple of the lot size, and their product a
+    /// whole amount of the smallest unit of the quote asset.
+    pub fn limit_order(
+        &self,
+        side: Side,
+        price: &str,
+        quantity: u64,
+    ) -> Result<Order, MarketError> {
+        let unit_price = self.parse_price(price)?;
+        if unit_price == 0 || unit_price % self.tick_size != 0 {
+            return Err(MarketError::InvalidPrice(
+                price.to_string(),
+                self.display_tick_size(),
+            ));
+        }
+        if quantity == 0 || quantity % self.lot_size != 0 {
+            return Err(MarketError::InvalidQuantity(quantity, self.lot_size));
+        }
+
+        let quote_amount = unit_price as u128 * quantity as u128;
+        let base_unit = unit(self.base_decimals) as u128;
+        if quote_amount % base_unit != 0 {
+            return Err(MarketError::InvalidAmount(format!(
+                "{} {} at {} is not a whole amount of {}",
+                quantity, self.base_symbol, price, self.quote_symbol
+            )));
+        }
+        let quote_amount = u64::try_from(quote_amount / base_unit).map_err(|_| {
+            MarketError::InvalidAmount(format!("{} * {} overflows", price, quantity))
+        })?;
+        let base_asset = FungibleAsset::new(self.base_faucet_id, quantity)
+            .map_err(|e| MarketError::InvalidAmount(e.to_string()))?;
+        let quote_asset = FungibleAsset::new(self.quote_faucet_id, quote_amount)
+            .map_err(|e| MarketError::InvalidAmount(e.to_string()))?;
+
+        let (source_asset, target_asset) = match side {
+            Side::Bid => (quote_asset, base_asset),
+            Side::Ask => (base_asset, quote_asset),
+        };
+        Ok(Order::new(
+            None,
+            Asset::Fungible(source_asset),
+            Asset::Fungible(target_asset),
+        ))
+    }
+
+    /// Builds a market order for `quantity` base asset: bids receive it and asks spend it, at the
+    /// prices of the book within `guard`. The quantity must be a multiple of the lot size.
+    pub fn market_order(
+        &self,
+        side: Side,
+        quantity: u64,
+        guard: SlippageGuard,
+    ) -> Result<MarketOrder, MarketError> {
+        if quantity == 0 || quantity % self.lot_size != 0 {
+            return Err(MarketError::InvalidQuantity(quantity, self.lot_size));
+        }
+
+        let (source_faucet_id, target_faucet_id, amount) = match side {
+            Side::Bid => (
+                self.quote_faucet_id,
+                self.base_faucet_id,
+                MarketAmount::Receive(quantity),
+            ),
+            Side::Ask => (
+                self.base_faucet_id,
+                self.quote_faucet_id,
+                MarketAmount::Spend(quantity),
+            ),
+        };
+        Ok(MarketOrder {
+            source_faucet_id,
+            target_faucet_id,
+            amount,
+            guard,
+            account_id: None,
+        })
+    }
+
+    /// Returns the bound on the average price of a market order of `side` for the worst price it
+    /// accepts, in quote asset per base asset (see [Market::parse_price]): the highest price for
+    /// bids, the lowest for asks.
+    pub fn worst_price_guard(
+        &self,
+        side: Side,
+        worst_price: &str,
+    ) -> Result<SlippageGuard, MarketError> {
+        let unit_price = self.parse_price(worst_price)?;
+        let base_unit = unit(self.base_decimals);
+
+        // the guard is in source asset per target asset
+        let max_price = match side {
+            Side::Bid => Price::new(base_unit, unit_price),
+            Side::Ask => Price::new(unit_price, base_unit),
+        };
+        Ok(SlippageGuard::MaxAveragePrice(max_price))
+    }
+
+    /// Returns the price of an order of the market, in quote asset per base asset.
+    pub fn price_of(&self, order: &Order) -> Price {
+        if order.source_asset().faucet_id() == self.quote_faucet_id {
+            order.price().inverse()
+        } else {
+            order.price()
+        }
+    }
+
+    /// Returns an empty book for the market.
+    pub fn book(&self) -> OrderBook {
+        OrderBook::new(self.base_faucet_id, self.quote_faucet_id)
+            .expect("Market faucets were checked on creation")
+    }
+}
+
+// Market Registry
+// ================================================================================================
+
+/// Trading pairs known to the order book, persisted in the CLOB data file (see
+/// [ClobData](crate::clob_data::ClobData)).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MarketRegistry {
+    markets: Vec<Market>,
+}
+
+impl MarketRegistry {
+    /// Adds a market, replacing the market with the same symbol if there is one.
+    pub fn add(&mut self, market: Market) {
+        match self
+            .markets
+            .iter_mut()
+            .find(|m| m.symbol() == market.symbol())
+        {
+            Some(existing) => *existing = market,
+            None => self.markets.push(market),
+        }
+    }
+
+    /// Returns the market with the given `BASE/QUOTE` symbol, or the first registered market when
+    /// no symbol is given.
+    pub fn get_or_default(&self, symbol: Option<&str>) -> Result<&Market, MarketError> {
+        match symbol {
+            Some(symbol) => self.get(symbol),
+            None => self.markets.first().ok_or(MarketError::NoMarkets),
+        }
+    }
+
+    /// Returns the market with the given `BASE/QUOTE` symbol.
+    pub fn get(&self, symbol: &str) -> Result<&Market, MarketError> {
+        self.markets
+            .iter()
+            .find(|market| market.symbol() == symbol)
+            .ok_or(MarketError::UnknownMarket(symbol.to_string()))
+    }
+
+    /// Returns the market trading the two faucets, in either direction.
+    pub fn find(&self, faucet_id_1: AccountId, faucet_id_2: AccountId) -> Option<&Market> {
+        self.markets.iter().find(|market| {
+            (market.base_faucet_id, market.quote_faucet_id) == (faucet_id_1, faucet_id_2)
+                || (market.base_faucet_id, market.quote_faucet_id) == (faucet_id_2, faucet_id_1)
+        })
+    }
+
+    pub fn markets(&self) -> &[Market] {
+        &self.markets
+    }
+}
+
+// Helpers
+// ================================================================================================
+
+/// Maximum number of decimals of an asset, so that one whole asset fits a u64.
+const MAX_DECIMALS: u8 = 18;
+
+/// Returns the number of smallest units in one whole asset with `decimals` decimals.
+fn unit(decimals: u8) -> u64 {
+    10u64.pow(decimals as u32)
+}
+
+/// Formats an amount of smallest units as a decimal in whole assets, without trailing zeros.
+fn format_decimal(units: u128, decimals: u8) -> String {
+    let unit = unit(decimals) as u128;
+    let fraction = format!("{:0width$}", units % unit, width = decimals as usize);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        (units / unit).to_string()
+    } else {
+        format!("{}.{}", units / unit, fraction)
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use miden_client::{accounts::AccountId, notes::NoteId};
+
+    use super::{Market, MarketRegistry};
+    use crate::{
+        book::Side,
+        errors::{MarketError, OrderError},
+        order::{fill_order, MarketAmount, Order, SlippageGuard},
+        price::Price,
+    };
+
+    fn build_market() -> Market {
+        Market::new(
+            "ASSETA",
+            AccountId::from_hex("0x227bd163275aa1bf").unwrap(),
+            10,
+            "ASSETB",
+            AccountId::from_hex("0x2540b08edc3b087d").unwrap(),
+            10,
+            1,
+            1,
+        )
+        .unwrap()
+    }
+
+    fn resting_order(market: &Market, side: Side, price: u64, id: u64) -> Order {
+        let order = market.limit_order(side, &price.to_string(), 10).unwrap();
+        let note_id = NoteId::try_from_hex(&format!("0x{:064x}", id)).unwrap();
+
+        Order::new(Some(note_id), order.source_asset(), order.target_asset())
+    }
+
+    #[test]
+    fn registry_finds_markets() {
+        let market = build_market();
+        let mut registry = MarketRegistry::default();
+        assert_eq!(registry.get_or_default(None), Err(MarketError::NoMarkets));
+
+        registry.add(market.clone());
+        registry.add(market.clone());
+
+        assert_eq!(registry.markets(), &[market.clone()]);
+        assert_eq!(registry.get_or_default(None), Ok(&market));
+        assert_eq!(registry.get("ASSETA/ASSETB"), Ok(&market));
+        assert_eq!(
+            registry.get("ASSETB/ASSETA"),
+            Err(MarketError::UnknownMarket("ASSETB/ASSETA".to_string()))
+        );
+        assert_eq!(
+            registry.find(market.quote_faucet_id(), market.base_faucet_id()),
+            Some(&market)
+        );
+        assert_eq!(market.book().ask_tag(), market.ask_tag());
+    }
+
+    #[test]
+    fn market_rejects_invalid_sizes() {
+        let market = build_market();
+        let result = Market::new(
+            "ASSETA",
+            market.base_faucet_id(),
+            10,
+            "ASSETB",
+            market.quote_faucet_id(),
+            10,
+            0,
+            1,
+        );
+
+        assert!(matches!(result, Err(MarketError::InvalidMarket(_, _))));
+    }
+
+    #[test]
+    fn limit_orders_respect_tick_and_lot_sizes() {
+        // prices have 2 decimals and a tick of 0.05 ASSETB per ASSETA
+        let market = Market::new(
+            "ASSETA",
+            AccountId::from_hex("0x227bd163275aa1bf").unwrap(),
+            2,
+            "ASSETB",
+            AccountId::from_hex("0x2540b08edc3b087d").unwrap(),
+            2,
+            5,
+            10,
+        )
+        .unwrap();
+        assert_eq!(market.display_tick_size(), "0.05");
+
+        // 20 units, or 0.2 ASSETA, at 1.5 ASSETB per ASSETA cost 0.3 ASSETB
+        let bid = market.limit_order(Side::Bid, "1.5", 20).unwrap();
+        assert_eq!(bid.source_asset().faucet_id(), market.quote_faucet_id());
+        assert_eq!(bid.source_asset().unwrap_fungible().amount(), 30);
+        assert_eq!(bid.target_asset().unwrap_fungible().amount(), 20);
+        assert_eq!(market.price_of(&bid), Price::new(20, 30));
+        assert_eq!(market.format_price(market.price_of(&bid)), "1.5");
+
+        let ask = market.limit_order(Side::Ask, "1.50", 20).unwrap();
+        assert_eq!(ask.source_asset().faucet_id(), market.base_faucet_id());
+        assert_eq!(market.price_of(&ask), market.price_of(&bid));
+
+        // prices below 1
+        let ask = market.limit_order(Side::Ask, "0.5", 20).unwrap();
+        assert_eq!(ask.target_asset().unwrap_fungible().amount(), 10);
+        assert_eq!(market.format_price(market.price_of(&ask)), "0.5");
+
+        assert_eq!(
+            market.limit_order(Side::Bid, "1.52", 20),
+            Err(MarketError::InvalidPrice(
+                "1.52".to_string(),
+                "0.05".to_string()
+            ))
+        );
+        assert_eq!(
+            market.limit_order(Side::Bid, "1.501", 20),
+            Err(MarketError::InvalidPrice(
+                "1.501".to_string(),
+                "0.05".to_string()
+            ))
+        );
+        assert_eq!(
+            market.limit_order(Side::Bid, "1,5", 20),
+            Err(MarketError::MalformedPrice("1,5".to_string()))
+        );
+        assert_eq!(
+            market.limit_order(Side::Ask, "1.5", 25),
+            Err(MarketError::InvalidQuantity(25, 10))
+        );
+        // 10 units at 0.05 are worth half a unit of ASSETB
+        assert!(matches!(
+            market.limit_order(Side::Bid, "0.05", 10),
+            Err(MarketError::InvalidAmount(_))
+        ));
+    }
+
+    #[test]
+    fn market_orders_are_in_base_asset() {
+        let market = Market::new(
+            "ASSETA",
+            AccountId::from_hex("0x227bd163275aa1bf").unwrap(),
+            10,
+            "ASSETB",
+            AccountId::from_hex("0x2540b08edc3b087d").unwrap(),
+            10,
+            5,
+            10,
+        )
+        .unwrap();
+
+        let guard = market.worst_price_guard(Side::Bid, "15").unwrap();
+        let bid = market.market_order(Side::Bid, 20, guard).unwrap();
+        assert_eq!(bid.source_faucet_id, market.quote_faucet_id());
+        assert_eq!(bid.amount, MarketAmount::Receive(20));
+        // at most 300 quote asset for 20 base asset
+        assert_eq!(guard, SlippageGuard::MaxAveragePrice(Price::new(20, 300)));
+
+        let guard = market.worst_price_guard(Side::Ask, "15").unwrap();
+        let ask = market.market_order(Side::Ask, 20, guard).unwrap();
+        assert_eq!(ask.source_faucet_id, market.base_faucet_id());
+        assert_eq!(ask.amount, MarketAmount::Spend(20));
+        // at most 20 base asset for 300 quote asset
+        assert_eq!(guard, SlippageGuard::MaxAveragePrice(Price::new(300, 20)));
+
+        assert_eq!(
+            market.market_order(Side::Ask, 25, guard),
+            Err(MarketError::InvalidQuantity(25, 10))
+        );
+    }
+
+    #[test]
+    fn sell_limit_never_fills_lower_bids() {
+        let market = build_market();
+        let sell = market.limit_order(Side::Ask, 2, 30).unwrap();
+
+        let mut book = market.book();
+        book.insert(resting_order(&market, Side::Bid, 1, 1))
+            .unwrap();
+        assert_eq!(
+            fill_order(sell, &book, 0),
+            Err(OrderError::FailedFill(sell))
+        );
+
+        let bid_at_price = resting_order(&market, Side::Bid, 2, 2);
+        let better_bid = resting_order(&market, Side::Bid, 3, 3);
+        book.insert(bid_at_price).unwrap();
+        book.insert(better_bid).unwrap();
+
+        let (plan, remaining_source) = fill_order(sell, &book, 0).unwrap();
+        assert_eq!(plan.orders(), vec![better_bid, bid_at_price]);
+        assert_eq!(remaining_source, 10);
+    }
+
+    #[test]
+    fn buy_limit_fills_every_ask_up_to_its_price() {
+        let market = build_market();
+        let buy = market.limit_order(Side::Bid, 2, 30).unwrap();
+
+        let better_ask = resting_order(&market, Side::Ask, 1, 1);
+        let ask_at_price = resting_order(&market, Side::Ask, 2, 2);
+        let mut book = market.book();
+        book.insert(resting_order(&market, Side::Ask, 3, 3))
+            .unwrap();
+        book.insert(ask_at_price).unwrap();
+        book.insert(better_ask).unwrap();
+
+        let (plan, remaining_source) = fill_order(buy, &book, 0).unwrap();
+        assert_eq!(plan.orders(), vec![better_ask, ask_at_price]);
+        assert_eq!(
+            plan.fills.iter().map(|fill| fill.paid).collect::<Vec<_>>(),
+            vec![10, 20]
+        );
+        assert_eq!(remaining_source, 30);
+    }
+}
@@ -0,0 +1,200 @@
+use miden_client::{
+    accounts::AccountId,
+    assets::{Asset, FungibleAsset},
+    notes::{NoteError, NoteExecutionHint, NoteInputs, NoteTag},
+    Felt, Word, ZERO,
+};
+use miden_objects::Digest;
+
+use crate::{errors::SwappInputsError, order::OrderValidity};
+
+/// Number of inputs of a SWAPP note.
+pub const SWAPP_NUM_INPUTS: usize = 20;
+
+// Indexes of the inputs, the SWAPP script loads them in memory one word per address: the payback
+// script hash at `PAYBACK_SCRIPT_HASH_PTR`, the requested asset at `REQUESTED_ASSET_PTR`, the tags, hint and creator
+// at address 2, the script hash at `SCRIPT_HASH_PTR` and the validity at `VALIDITY_PTR`.
+const PAYBACK_SCRIPT_HASH_INPUT: usize = 0;
+const REQUESTED_ASSET_INPUT: usize = 4;
+const PAYBACK_TAG_INPUT: usize = 8;
+const PAYBACK_HINT_INPUT: usize = 9;
+const SWAPP_TAG_INPUT: usize = 10;
+const CREATOR_INPUT: usize = 11;
+const SCRIPT_HASH_INPUT: usize = 12;
+const GOOD_AFTER_BLOCK_INPUT: usize = 16;
+const EXPIRY_BLOCK_INPUT: usize = 17;
+
+/// Inputs of a SWAPP note, in the layout expected by the memory map of `SWAPP.masm`.
+///
+/// The SWAPP notes created for the remainder of partial fills have the inputs of the filled note,
+/// with the requested amount decreased by the fill amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwappNoteInputs {
+    /// Hash of the script of the notes paying the requested asset back to the creator, the P2ID
+    /// script. The SWAPP script builds their recipient for each fill.
+    pub payback_script_hash: Digest,
+    /// Asset requested in exchange for the asset of the note.
+    pub requested_asset: FungibleAsset,
+    /// Tag of the payback notes.
+    pub payback_tag: NoteTag,
+    /// Execution hint of the payback notes.
+    pub payback_hint: NoteExecutionHint,
+    /// Tag of the SWAPP notes created for the remainder of partial fills.
+    pub swapp_tag: NoteTag,
+    /// Account which created the order, it can reclaim the note at any time.
+    pub creator: AccountId,
+    /// Hash of the SWAPP script, used to build the recipient of the remainder of partial fills.
+    pub script_hash: Digest,
+    /// Blocks in which the note can be filled, a zero block stands for no bound.
+    pub validity: OrderValidity,
+}
+
+impl SwappNoteInputs {
+    /// Encodes the inputs in the order of the memory map of the SWAPP script.
+    pub fn encode(&self) -> Result<NoteInputs, NoteError> {
+        let payback_script_hash: Word = self.payback_script_hash.into();
+        let requested_asset: Word = Asset::Fungible(self.requested_asset).into();
+        let script_hash: Word = self.script_hash.into();
+
+        let mut inputs = Vec::with_capacity(SWAPP_NUM_INPUTS);
+        inputs.extend(payback_script_hash);
+        inputs.extend(requested_asset);
+        inputs.extend([
+            self.payback_tag.inner().into(),
+            self.payback_hint.into(),
+            self.swapp_tag.inner().into(),
+            self.creator.into(),
+        ]);
+        inputs.extend(script_hash);
+        inputs.extend([
+            Felt::from(self.validity.good_after_block.unwrap_or(0)),
+            Felt::from(self.validity.expiry_block.unwrap_or(0)),
+            ZERO,
+            ZERO,
+        ]);
+
+        NoteInputs::new(inputs)
+    }
+
+    /// Decodes the inputs of a SWAPP note.
+    pub fn decode(inputs: &NoteInputs) -> Result<Self, SwappInputsError> {
+        let inputs = inputs.values();
+        if inputs.len() != SWAPP_NUM_INPUTS {
+            return Err(SwappInputsError::WrongNumberOfInputs(inputs.len()));
+        }
+
+        let word = |index: usize| -> Word {
+            [
+                inputs[index],
+                inputs[index + 1],
+                inputs[index + 2],
+                inputs[index + 3],
+            ]
+        };
+        let tag = |index: usize| {
+            u32::try_from(inputs[index].as_int())
+                .map(NoteTag::from)
+                .map_err(|_| SwappInputsError::InvalidTag(inputs[index].as_int()))
+        };
+        let block = |index: usize| {
+            u32::try_from(inputs[index].as_int())
+                .map(|block| (block != 0).then_some(block))
+                .map_err(|_| SwappInputsError::InvalidBlockNumber(inputs[index].as_int()))
+        };
+        let account_id = |felt: Felt| {
+            AccountId::try_from(felt).map_err(|e| SwappInputsError::InvalidAccountId(e.to_string()))
+        };
+
+        let requested_asset = word(REQUESTED_ASSET_INPUT);
+        let requested_asset =
+            FungibleAsset::new(account_id(requested_asset[3])?, requested_asset[0].as_int())
+                .map_err(|e| SwappInputsError::InvalidAsset(e.to_string()))?;
+
+        let payback_hint = inputs[PAYBACK_HINT_INPUT].as_int();
+        let payback_hint = NoteExecutionHint::try_from(payback_hint)
+            .map_err(|_| SwappInputsError::InvalidExecutionHint(payback_hint))?;
+
+        Ok(SwappNoteInputs {
+            payback_script_hash: word(PAYBACK_SCRIPT_HASH_INPUT).into(),
+            requested_asset,
+            payback_tag: tag(PAYBACK_TAG_INPUT)?,
+            payback_hint,
+            swapp_tag: tag(SWAPP_TAG_INPUT)?,
+            creator: account_id(inputs[CREATOR_INPUT])?,
+            script_hash: word(SCRIPT_HASH_INPUT).into(),
+            validity: OrderValidity::new(
+                block(GOOD_AFTER_BLOCK_INPUT)?,
+                block(EXPIRY_BLOCK_INPUT)?,
+            ),
+        })
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use miden_client::{
+        accounts::AccountId,
+        assets::FungibleAsset,
+        notes::{NoteExecutionHint, NoteInputs, NoteTag},
+        Felt, ZERO,
+    };
+    use miden_objects::Digest;
+
+    use super::{SwappNoteInputs, SWAPP_NUM_INPUTS};
+    use crate::{errors::SwappInputsError, order::OrderValidity};
+
+    fn build_inputs() -> SwappNoteInputs {
+        let faucet_id = AccountId::from_hex("0x2540b08edc3b087d").unwrap();
+        let creator = AccountId::from_hex("0x9b6a0ea2ad6e5b3a").unwrap();
+
+        SwappNoteInputs {
+            payback_script_hash: Digest::from([
+                Felt::new(1),
+                Felt::new(2),
+                Felt::new(3),
+                Felt::new(4),
+            ]),
+            requested_asset: FungibleAsset::new(faucet_id, 20).unwrap(),
+            payback_tag: NoteTag::from(7),
+            payback_hint: NoteExecutionHint::always(),
+            swapp_tag: NoteTag::from(8),
+            creator,
+            script_hash: Digest::from([Felt::new(5), Felt::new(6), Felt::new(7), Felt::new(8)]),
+            validity: OrderValidity::new(None, Some(100)),
+        }
+    }
+
+    #[test]
+    fn inputs_match_the_memory_map_of_the_swapp_script() {
+        let inputs = build_inputs();
+        let encoded = inputs.encode().unwrap();
+
+        let values = encoded.values();
+        assert_eq!(values.len(), SWAPP_NUM_INPUTS);
+        assert_eq!(values[4], Felt::new(20));
+        assert_eq!(values[7], inputs.requested_asset.faucet_id().into());
+        assert_eq!(values[11], inputs.creator.into());
+        assert_eq!(&values[16..], &[ZERO, Felt::new(100), ZERO, ZERO]);
+
+        assert_eq!(SwappNoteInputs::decode(&encoded), Ok(inputs));
+    }
+
+    #[test]
+    fn invalid_inputs_are_rejected() {
+        let inputs = NoteInputs::new(vec![ZERO; 16]).unwrap();
+        assert_eq!(
+            SwappNoteInputs::decode(&inputs),
+            Err(SwappInputsError::WrongNumberOfInputs(16))
+        );
+
+        let mut values: Vec<Felt> = build_inputs().encode().unwrap().into();
+        values[8] = Felt::new(u64::from(u32::MAX) + 1);
+        assert_eq!(
+            SwappNoteInputs::decode(&NoteInputs::new(values).unwrap()),
+            Err(SwappInputsError::InvalidTag(u64::from(u32::MAX) + 1))
+        );
+    }
+}
@@ -6,13 +6,40 @@ use miden_client::{
         build_swap_tag, Note, NoteAssets, NoteError, NoteExecutionHint, NoteExecutionMode,
         NoteInputs, NoteMetadata, NoteRecipient, NoteScript, NoteTag, NoteType,
     },
-    transactions::{TransactionRequest, TransactionRequestError},
-    Felt, Word,
+    transactions::TransactionRequest,
+    Felt, Word, ONE, ZERO,
 };
 use miden_lib::transaction::TransactionKernel;
-use miden_objects::transaction::OutputNote;
+use miden_objects::{transaction::OutputNote, Digest};
 use rand::{seq::SliceRandom, Rng};
-use miden_client::ZERO;
+use std::sync::OnceLock;
+
+use crate::{
+    errors::{SwappNoteError, TransactionError},
+    order::{FillPlan, Order, OrderValidity},
+    price::Price,
+};
+
+mod inputs;
+
+pub use inputs::{SwappNoteInputs, SWAPP_NUM_INPUTS};
+
+/// Returns the SWAPP note script, compiled on first use and shared by every SWAPP note built or
+/// recognised afterwards.
+pub fn swapp_script() -> &'static NoteScript {
+    static SWAPP_SCRIPT: OnceLock<NoteScript> = OnceLock::new();
+
+    SWAPP_SCRIPT.get_or_init(|| {
+        let note_code = include_str!("scripts/SWAPP.masm");
+        NoteScript::compile(note_code, TransactionKernel::assembler())
+            .expect("SWAPP script should compile")
+    })
+}
+
+/// Returns the root of the SWAPP note script, which identifies SWAPP notes.
+pub fn swapp_script_root() -> Digest {
+    swapp_script().hash()
+}
 
 pub fn create_partial_swap_notes_transaction_request(
     num_notes: u8,
@@ -22,23 +49,25 @@ pub fn create_partial_swap_notes_transaction_request(
     requesting_faucet: AccountId,
     total_asset_requesting: u64,
     felt_rng: &mut impl FeltRng,
-) -> Result<TransactionRequest, TransactionRequestError> {
+) -> Result<TransactionRequest, TransactionError> {
     // Setup note args
     let mut own_output_notes = vec![];
 
     let note_type = NoteType::Public;
     let offering_distribution =
-        generate_random_distribution(num_notes as usize, total_asset_offering);
+        generate_random_distribution(num_notes as usize, total_asset_offering)?;
     let requesting_distribution =
-        generate_random_distribution(num_notes as usize, total_asset_requesting);
+        generate_random_distribution(num_notes as usize, total_asset_requesting)?;
 
     for i in 0..num_notes {
-        let offered_asset = Asset::Fungible(
-            FungibleAsset::new(offering_faucet, offering_distribution[i as usize]).unwrap(),
-        );
-        let requested_asset = Asset::Fungible(
-            FungibleAsset::new(requesting_faucet, requesting_distribution[i as usize]).unwrap(),
-        );
+        let offered_asset = Asset::Fungible(FungibleAsset::new(
+            offering_faucet,
+            offering_distribution[i as usize],
+        )?);
+        let requested_asset = Asset::Fungible(FungibleAsset::new(
+            requesting_faucet,
+            requesting_distribution[i as usize],
+        )?);
 
         let swapp_note = create_swapp_note(
             sender,
@@ -46,114 +75,297 @@ pub fn create_partial_swap_notes_transaction_request(
             requested_asset,
             note_type,
             Felt::new(0),
+            OrderValidity::default(),
             felt_rng,
         )?;
 
         own_output_notes.push(OutputNote::Full(swapp_note));
     }
 
-    TransactionRequest::new().with_own_output_notes(own_output_notes)
+    Ok(TransactionRequest::new().with_own_output_notes(own_output_notes)?)
 }
 
+/// Creates a SWAPP note offering `offered_asset` for `requested_asset`, which can only be filled
+/// in the blocks allowed by `validity`.
+///
+/// Fills pay the requested asset back to `sender` with P2ID notes, whose serial number is derived
+/// from the one of the filled note (see [create_expected_payback_note]). The serial number of the
+/// SWAPP note is drawn from `rng`.
 pub fn create_swapp_note<R: FeltRng>(
     sender: AccountId,
     offered_asset: Asset,
     requested_asset: Asset,
     note_type: NoteType,
     aux: Felt,
+    validity: OrderValidity,
     rng: &mut R,
 ) -> Result<Note, NoteError> {
-    let assembler = TransactionKernel::assembler();
-    let note_code = include_str!("scripts/SWAPP.masm");
-    let note_script = NoteScript::compile(note_code, assembler).unwrap();
-    let note_script_hash = note_script.hash();
-
     // build the tag for the SWAPP use case
     let swapp_tag = build_swap_tag(note_type, &offered_asset, &requested_asset)?;
 
-    let payback_serial_num = rng.draw_word();
-    let payback_recipient = build_p2id_recipient(sender, payback_serial_num)?;
-
-    let payback_recipient_word: Word = payback_recipient.digest().into();
-    let requested_asset_word: Word = requested_asset.into();
-    let payback_tag = NoteTag::from_account_id(sender, NoteExecutionMode::Local)?;
-
-
-    let inputs = NoteInputs::new(vec![
-        payback_recipient_word[0],
-        payback_recipient_word[1],
-        payback_recipient_word[2],
-        payback_recipient_word[3],
-        requested_asset_word[0],
-        requested_asset_word[1],
-        requested_asset_word[2],
-        requested_asset_word[3],
-        payback_tag.inner().into(),
-        NoteExecutionHint::always().into(),
-        swapp_tag.inner().into(),
-        ZERO,
-        note_script_hash[0],
-        note_script_hash[1],
-        note_script_hash[2],
-        note_script_hash[3],
-    ]).unwrap();
+    let inputs = SwappNoteInputs {
+        payback_script_hash: p2id_script().hash(),
+        requested_asset: requested_asset.unwrap_fungible(),
+        payback_tag: NoteTag::from_account_id(sender, NoteExecutionMode::Local)?,
+        payback_hint: NoteExecutionHint::always(),
+        swapp_tag,
+        creator: sender,
+        script_hash: swapp_script_root(),
+        validity,
+    }
+    .encode()?;
 
-    let serial_num = [Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)];
+    let serial_num = rng.draw_word();
 
     // build the outgoing note
-    let metadata = NoteMetadata::new(sender, note_type, swapp_tag, NoteExecutionHint::always(), aux)?;
+    let metadata = NoteMetadata::new(
+        sender,
+        note_type,
+        swapp_tag,
+        NoteExecutionHint::always(),
+        aux,
+    )?;
     let assets = NoteAssets::new(vec![offered_asset])?;
-    let recipient = NoteRecipient::new(serial_num, note_script, inputs);
+    let recipient = NoteRecipient::new(serial_num, swapp_script().clone(), inputs);
     let note = Note::new(assets, metadata, recipient);
 
     Ok(note)
 }
 
+/// Builds the SWAPP note created for the remainder of `original_swapp_note` when it is consumed
+/// with `fill_amount` of the requested asset.
+///
+/// The remaining offered amount is computed with [Price::partial_exchange], and the serial number
+/// with [get_successor_serial_num], which mirror the SWAPP script so that the expected note matches
+/// the one actually created.
 pub fn create_expected_partial_swapp_note(
     sender: AccountId,
     original_swapp_note: Note,
     fill_amount: u64,
-    price: f64,
-) -> Result<Note, NoteError> {
+) -> Result<Note, TransactionError> {
     let swapp_tag = original_swapp_note.metadata().tag();
     let note_type = original_swapp_note.metadata().note_type();
     let aux = original_swapp_note.metadata().aux();
     let note_script = original_swapp_note.recipient().script().clone();
-    let mut inputs: Vec<Felt> = original_swapp_note.recipient().inputs().clone().into();
+    let mut inputs = SwappNoteInputs::decode(original_swapp_note.recipient().inputs())?;
 
-    let requested_amount :u64 = inputs[4].into();
-    let remaining_requested_amount = requested_amount - fill_amount;
-    inputs[4] = Felt::new(remaining_requested_amount);
+    let requested_amount = inputs.requested_asset.amount();
+    let remaining_requested_amount =
+        requested_amount
+            .checked_sub(fill_amount)
+            .ok_or(TransactionError::FillExceedsRequested(
+                fill_amount,
+                requested_amount,
+            ))?;
+    inputs.requested_asset = FungibleAsset::new(
+        inputs.requested_asset.faucet_id(),
+        remaining_requested_amount,
+    )?;
 
-    let offered_asset = original_swapp_note.assets().iter().next().unwrap().unwrap_fungible();
-    let requested_asset = Asset::Fungible(
-        FungibleAsset::new(
-            offered_asset.faucet_id(),
-            offered_asset.amount() - (fill_amount as f64 /price) as u64,
-        ).unwrap(),
-    );
+    let offered_asset = match original_swapp_note.assets().iter().next() {
+        Some(Asset::Fungible(asset)) => *asset,
+        _ => {
+            return Err(TransactionError::InvalidSwappNote(
+                SwappNoteError::NonFungibleAsset,
+            ))
+        }
+    };
+    let offered_amount_out = Price::new(offered_asset.amount(), requested_amount)
+        .partial_exchange(fill_amount)
+        .ok_or(TransactionError::UnfillableNote(original_swapp_note.id()))?;
+    let remaining_offered_amount = offered_asset
+        .amount()
+        .checked_sub(offered_amount_out)
+        .ok_or(TransactionError::FillExceedsOffered(
+            offered_amount_out,
+            offered_asset.amount(),
+        ))?;
+    let remaining_offered_asset = Asset::Fungible(FungibleAsset::new(
+        offered_asset.faucet_id(),
+        remaining_offered_amount,
+    )?);
 
-    let metadata = NoteMetadata::new(sender, note_type, swapp_tag, NoteExecutionHint::always(), aux)?;
-    let assets = NoteAssets::new(vec![requested_asset])?;
-    let recipient = NoteRecipient::new(original_swapp_note.serial_num(), note_script, inputs.try_into().expect("Input quantity shouldn't change"));
+    let metadata = NoteMetadata::new(
+        sender,
+        note_type,
+        swapp_tag,
+        NoteExecutionHint::always(),
+        aux,
+    )?;
+    let assets = NoteAssets::new(vec![remaining_offered_asset])?;
+    let inputs = inputs.encode()?;
+    let serial_num = get_successor_serial_num(original_swapp_note.serial_num());
+    let recipient = NoteRecipient::new(serial_num, note_script, inputs);
 
     Ok(Note::new(assets, metadata, recipient))
 }
 
+/// Returns the serial number of the SWAPP note created for the remainder of a partially filled
+/// SWAPP note with `serial_num`: its last element is incremented, like in the SWAPP script.
+///
+/// Every note of a chain of partial fills therefore has a distinct serial number, and a distinct
+/// nullifier.
+pub fn get_successor_serial_num(serial_num: Word) -> Word {
+    let [a, b, c, d] = serial_num;
+    [a, b, c, d + ONE]
+}
+
+/// Returns the serial number of the P2ID note created by the SWAPP script when the SWAPP note with
+/// `serial_num` is filled: its third element is incremented, like in the SWAPP script.
+///
+/// Each note of a chain of partial fills is consumed once, so every fill pays back with a distinct
+/// note, even when fills of successive notes are for the same amount.
+pub fn get_payback_serial_num(serial_num: Word) -> Word {
+    let [a, b, c, d] = serial_num;
+    [a, b, c + ONE, d]
+}
+
+/// Builds the P2ID note created by the SWAPP script when `consumer` fills `swapp_note` with
+/// `fill_amount` of its requested asset, paying it back to the creator of the note.
+///
+/// The serial number of the payback note is derived from the one of `swapp_note` with
+/// [get_payback_serial_num].
+pub fn create_expected_payback_note(
+    consumer: AccountId,
+    swapp_note: &Note,
+    fill_amount: u64,
+) -> Result<Note, TransactionError> {
+    let inputs = SwappNoteInputs::decode(swapp_note.recipient().inputs())?;
+    if inputs.payback_script_hash != p2id_script().hash() {
+        return Err(TransactionError::UnknownPaybackScript(swapp_note.id()));
+    }
+    let payback_serial_num = get_payback_serial_num(swapp_note.serial_num());
+    let recipient = build_p2id_recipient(inputs.creator, payback_serial_num)?;
+
+    let payback_asset = FungibleAsset::new(inputs.requested_asset.faucet_id(), fill_amount)?;
+
+    // the SWAPP script creates private payback notes without aux
+    let metadata = NoteMetadata::new(
+        consumer,
+        NoteType::Private,
+        inputs.payback_tag,
+        inputs.payback_hint,
+        ZERO,
+    )?;
+    let assets = NoteAssets::new(vec![payback_asset.into()])?;
+
+    Ok(Note::new(assets, metadata, recipient))
+}
+
+/// Returns the account which created the order of a SWAPP note.
+///
+/// The creator is kept in the note inputs, so it is also the creator of the SWAPP notes created
+/// for the remainder of partially filled notes, while their sender is the account which filled.
+pub fn get_swapp_note_creator(note: &Note) -> Option<AccountId> {
+    SwappNoteInputs::decode(note.recipient().inputs())
+        .ok()
+        .map(|inputs| inputs.creator)
+}
+
+/// Returns true if the note is a SWAPP note.
+pub fn is_swapp_note(note: &Note) -> bool {
+    note.recipient().script().hash() == swapp_script_root()
+}
+
+/// Builds the transaction request with which `creator` reclaims the offered assets of its own
+/// SWAPP notes, cancelling their orders in a single transaction.
+pub fn create_reclaim_transaction_request(
+    creator: AccountId,
+    swapp_notes: &[Note],
+) -> Result<TransactionRequest, TransactionError> {
+    if swapp_notes.is_empty() {
+        return Err(TransactionError::NothingToReclaim);
+    }
+
+    let mut notes_and_args = Vec::with_capacity(swapp_notes.len());
+    for note in swapp_notes {
+        if get_swapp_note_creator(note) != Some(creator) {
+            return Err(TransactionError::NotCreator(note.id(), creator));
+        }
+        // the SWAPP script ignores the note args when consumed by the creator
+        notes_and_args.push((note.id(), None));
+    }
+
+    Ok(TransactionRequest::new().with_authenticated_input_notes(notes_and_args))
+}
+
+/// Builds the transaction request consuming the notes of the orders of `plan` with their fill
+/// amounts.
+///
+/// When the last order is only partially filled, `expected_partial_swapp_note` must hold the
+/// successor SWAPP note that will be created for its remainder (see
+/// [create_expected_partial_swapp_note]). When the incoming order is not entirely filled,
+/// `remainder_swapp_note` is the SWAPP note posting its unfilled part (see [Order::remainder]),
+/// created in the same transaction. `cancelled_orders` are resting orders of the executing account
+/// which are reclaimed in the same transaction (see [crate::order::prevent_self_trades]).
+pub fn create_fill_transaction_request(
+    plan: &FillPlan,
+    cancelled_orders: &[Order],
+    expected_partial_swapp_note: Option<Note>,
+    remainder_swapp_note: Option<Note>,
+) -> Result<TransactionRequest, TransactionError> {
+    let mut notes_and_args = plan
+        .fills
+        .iter()
+        .map(|fill| {
+            fill.order
+                .id()
+                .ok_or(TransactionError::MissingId)
+                .map(|id| (id, Some(fill.args())))
+        })
+        .collect::<Result<Vec<_>, TransactionError>>()?;
+
+    // the SWAPP script ignores the note args when consumed by the creator
+    for order in cancelled_orders {
+        notes_and_args.push((order.id().ok_or(TransactionError::MissingId)?, None));
+    }
+
+    let mut transaction_request =
+        TransactionRequest::new().with_authenticated_input_notes(notes_and_args);
+
+    if let Some(swapp_note) = expected_partial_swapp_note {
+        transaction_request = transaction_request
+            .extend_advice_map(vec![(
+                swapp_note.recipient().digest(),
+                swapp_note.recipient().to_elements(),
+            )])
+            .with_expected_output_notes(vec![swapp_note]);
+    }
+
+    if let Some(swapp_note) = remainder_swapp_note {
+        transaction_request =
+            transaction_request.with_own_output_notes(vec![OutputNote::Full(swapp_note)])?;
+    }
+
+    Ok(transaction_request)
+}
+
 // HELPERS
 
 fn build_p2id_recipient(target: AccountId, serial_num: Word) -> Result<NoteRecipient, NoteError> {
-    let assembler = TransactionKernel::assembler();
-    let note_code = include_str!("scripts/SWAPP.masm");
-    let note_script = NoteScript::compile(note_code, assembler).unwrap();
     let note_inputs = NoteInputs::new(vec![target.into()])?;
 
-    Ok(NoteRecipient::new(serial_num, note_script, note_inputs))
+    Ok(NoteRecipient::new(
+        serial_num,
+        p2id_script().clone(),
+        note_inputs,
+    ))
+}
+
+fn p2id_script() -> &'static NoteScript {
+    static P2ID_SCRIPT: OnceLock<NoteScript> = OnceLock::new();
+
+    P2ID_SCRIPT.get_or_init(|| {
+        let note_code = include_str!("scripts/P2ID.masm");
+        NoteScript::compile(note_code, TransactionKernel::assembler())
+            .expect("P2ID script should compile")
+    })
 }
 
-fn generate_random_distribution(n: usize, total: u64) -> Vec<u64> {
-    if total < n as u64 {
-        panic!("Total must at least be equal to n to make sure that all values are non-zero.")
+fn generate_random_distribution(n: usize, total: u64) -> Result<Vec<u64>, TransactionError> {
+    // all values must be non-zero
+    if n == 0 || total < n as u64 {
+        return Err(TransactionError::InvalidDistribution(total, n));
     }
 
     let mut rng = rand::thread_rng();
@@ -184,5 +396,5 @@ fn generate_random_distribution(n: usize, total: u64) -> Vec<u64> {
     // Shuffle the vector to randomize the order
     result.shuffle(&mut rng);
 
-    result
+    Ok(result)
 }
@@ -1,12 +1,134 @@
-use miden_client::{assets::Asset, notes::NoteId, store::InputNoteRecord};
+use miden_client::{
+    accounts::AccountId,
+    assets::{Asset, FungibleAsset},
+    notes::{build_swap_tag, Note, NoteAssets, NoteId, NoteInputs, NoteMetadata},
+    store::InputNoteRecord,
+    transactions::NoteArgs,
+    Felt, ZERO,
+};
+use miden_objects::Digest;
 
-use crate::{errors::OrderError, utils::get_assets_from_swap_note};
+use crate::{
+    book::OrderBook,
+    errors::{OrderError, SwappNoteError},
+    note::{swapp_script_root, SwappNoteInputs},
+    price::Price,
+};
+
+/// Range of blocks in which the SWAPP note of an order can be filled.
+///
+/// The SWAPP script rejects fills outside of this range, while the creator can reclaim the note
+/// at any time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct OrderValidity {
+    /// The order can not be filled before this block.
+    pub good_after_block: Option<u32>,
+    /// The order can not be filled after this block.
+    pub expiry_block: Option<u32>,
+}
+
+impl OrderValidity {
+    pub fn new(good_after_block: Option<u32>, expiry_block: Option<u32>) -> Self {
+        OrderValidity {
+            good_after_block,
+            expiry_block,
+        }
+    }
+
+    /// Returns true if the order can be filled in a transaction executed against `block_num`.
+    pub fn is_active_at(&self, block_num: u32) -> bool {
+        self.good_after_block.is_none_or(|block| block_num >= block)
+            && self.expiry_block.is_none_or(|block| block_num <= block)
+    }
+
+    /// Returns true if the order can no longer be filled after `block_num`.
+    pub fn is_expired_at(&self, block_num: u32) -> bool {
+        self.expiry_block.is_some_and(|block| block_num > block)
+    }
+}
+
+/// How an incoming order takes liquidity from the book.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Execution {
+    /// Fill what can be filled, the order can be posted when nothing matches.
+    #[default]
+    Limit,
+    /// Only fill the order if its full source amount is matched.
+    FillOrKill,
+    /// Never take liquidity, the order can only be posted when it does not cross the book.
+    PostOnly,
+}
+
+/// What happens when an incoming order crosses resting orders created by the same account.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelfTradePrevention {
+    /// Skip the resting orders of the account, the incoming order is filled by the other orders.
+    #[default]
+    Skip,
+    /// Cancel the resting orders of the account, their SWAPP notes are reclaimed in the fill
+    /// transaction.
+    CancelResting,
+    /// Cancel the incoming order.
+    CancelIncoming,
+}
+
+/// Amount a market order is for, the other amount is given by the prices of the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketAmount {
+    /// Spend exactly this amount of the source asset.
+    Spend(u64),
+    /// Receive at least this amount of the target asset.
+    Receive(u64),
+}
+
+/// Bound on the average price of a market order, checked before any transaction is built.
+///
+/// Prices are in source asset spent per target asset received, like the prices of the resting
+/// orders a market order is filled with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlippageGuard {
+    /// Maximum average price.
+    MaxAveragePrice(Price),
+    /// Maximum deviation of the average price from the best price of the book, in basis points.
+    MaxSlippageBps(u64),
+}
+
+impl SlippageGuard {
+    fn check(&self, best_price: Price, average_price: Price) -> Result<(), OrderError> {
+        let within_bound = match self {
+            SlippageGuard::MaxAveragePrice(max_price) => average_price <= *max_price,
+            SlippageGuard::MaxSlippageBps(bps) => average_price.is_within_bps_of(&best_price, *bps),
+        };
+
+        if within_bound {
+            Ok(())
+        } else {
+            Err(OrderError::SlippageExceeded(average_price, *self))
+        }
+    }
+}
+
+/// Order filled at whatever prices the book offers, within a bound on its average price.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketOrder {
+    pub source_faucet_id: AccountId,
+    pub target_faucet_id: AccountId,
+    pub amount: MarketAmount,
+    pub guard: SlippageGuard,
+    /// Account executing the order, whose own resting orders are skipped.
+    pub account_id: Option<AccountId>,
+}
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Order {
     id: Option<NoteId>,
     source_asset: Asset,
     target_asset: Asset,
+    /// Block in which the SWAPP note of the order was committed, if known.
+    block_num: Option<u32>,
+    validity: OrderValidity,
+    /// Account which created the order, if known.
+    creator: Option<AccountId>,
 }
 
 impl Order {
@@ -15,13 +137,46 @@ impl Order {
             id,
             source_asset,
             target_asset,
+            block_num: None,
+            validity: OrderValidity::default(),
+            creator: None,
         }
     }
 
+    /// Sets the range of blocks in which the order can be filled.
+    pub fn with_validity(mut self, validity: OrderValidity) -> Self {
+        self.validity = validity;
+        self
+    }
+
+    /// Sets the account which created the order.
+    pub fn with_creator(mut self, creator: AccountId) -> Self {
+        self.creator = Some(creator);
+        self
+    }
+
+    /// Sets the block in which the SWAPP note of the order was committed.
+    pub fn with_block_num(mut self, block_num: u32) -> Self {
+        self.block_num = Some(block_num);
+        self
+    }
+
     pub fn id(&self) -> Option<NoteId> {
         self.id
     }
 
+    pub fn block_num(&self) -> Option<u32> {
+        self.block_num
+    }
+
+    pub fn validity(&self) -> OrderValidity {
+        self.validity
+    }
+
+    pub fn creator(&self) -> Option<AccountId> {
+        self.creator
+    }
+
     pub fn source_asset(&self) -> Asset {
         self.source_asset
     }
@@ -30,36 +185,295 @@ impl Order {
         self.target_asset
     }
 
-    pub fn price(&self) -> f64 {
+    /// Returns the order for the part of this order whose source asset was not filled, offering
+    /// `remaining_source` at the same or a better price, or `None` when nothing is left.
+    ///
+    /// The requested amount is rounded up, so that resting the remainder never lowers the price
+    /// of the order.
+    pub fn remainder(&self, remaining_source: u64) -> Option<Order> {
+        let source_asset = self.source_asset.unwrap_fungible();
+        let target_asset = self.target_asset.unwrap_fungible();
+        if remaining_source == 0 || remaining_source > source_asset.amount() {
+            return None;
+        }
+
+        let remaining_target = (target_asset.amount() as u128 * remaining_source as u128)
+            .div_ceil(source_asset.amount() as u128) as u64;
+        let source_asset = FungibleAsset::new(source_asset.faucet_id(), remaining_source).ok()?;
+        let target_asset = FungibleAsset::new(target_asset.faucet_id(), remaining_target).ok()?;
+
+        Some(Order {
+            creator: self.creator,
+            ..Order::new(None, source_asset.into(), target_asset.into())
+                .with_validity(self.validity)
+        })
+    }
+
+    /// Returns the price of the order, in target asset per source asset.
+    pub fn price(&self) -> Price {
         let source_asset_amount = self.source_asset.unwrap_fungible().amount();
         let target_asset_amount = self.target_asset.unwrap_fungible().amount();
 
-        target_asset_amount as f64 / source_asset_amount as f64
+        Price::new(source_asset_amount, target_asset_amount)
+    }
+}
+
+// Fill Plan
+// ================================================================================================
+
+/// Fill of a resting order, with the amounts moved by `SWAPP.masm` when its note is consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoteFill {
+    pub order: Order,
+    /// Amount of the requested asset of the order paid to its creator, the fill amount of the
+    /// note args.
+    pub paid: u64,
+    /// Amount of the offered asset of the order sent to the consumer of the note.
+    pub received: u64,
+    /// SWAPP note created for the remainder, when the order is partially filled.
+    pub successor: Option<SuccessorNote>,
+}
+
+/// SWAPP note created by `SWAPP.masm` for the remainder of a partially filled note. It keeps the
+/// inputs of its parent note apart from the requested asset, and is built ahead of the transaction
+/// with [crate::note::create_expected_partial_swapp_note].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SuccessorNote {
+    /// Id of the partially filled note.
+    pub parent_id: NoteId,
+    pub offered_asset: FungibleAsset,
+    pub requested_asset: FungibleAsset,
+}
+
+impl NoteFill {
+    /// Computes the fill of `order` when `fill_amount` of its requested asset is provided, with
+    /// the same formula as `SWAPP.masm`.
+    ///
+    /// Partial fills which round down to nothing of the offered asset, or up to all of it, are
+    /// rejected with [OrderError::InvalidFillAmount].
+    pub fn new(order: Order, fill_amount: u64) -> Result<Self, OrderError> {
+        let offered = order.source_asset().unwrap_fungible();
+        let requested = order.target_asset().unwrap_fungible();
+        if fill_amount > requested.amount() {
+            return Err(OrderError::InvalidFillAmount(
+                fill_amount,
+                requested.amount(),
+            ));
+        }
+
+        // the script sends the whole offered asset on a full fill
+        if fill_amount == requested.amount() {
+            return Ok(NoteFill {
+                order,
+                paid: fill_amount,
+                received: offered.amount(),
+                successor: None,
+            });
+        }
+
+        // a partial fill must send something and leave something to the successor note
+        let received = order
+            .price()
+            .partial_exchange(fill_amount)
+            .filter(|received| *received > 0 && *received < offered.amount())
+            .ok_or(OrderError::InvalidFillAmount(
+                fill_amount,
+                requested.amount(),
+            ))?;
+        let offered_asset = FungibleAsset::new(offered.faucet_id(), offered.amount() - received)
+            .map_err(|e| OrderError::InternalError(e.to_string()))?;
+        let requested_asset =
+            FungibleAsset::new(requested.faucet_id(), requested.amount() - fill_amount)
+                .map_err(|e| OrderError::InternalError(e.to_string()))?;
+        let successor = SuccessorNote {
+            parent_id: order.id().ok_or(OrderError::MissingId)?,
+            offered_asset,
+            requested_asset,
+        };
+
+        Ok(NoteFill {
+            order,
+            paid: fill_amount,
+            received,
+            successor: Some(successor),
+        })
+    }
+
+    /// Returns the note args consuming the note of the order with this fill.
+    pub fn args(&self) -> NoteArgs {
+        [Felt::new(self.paid), ZERO, ZERO, ZERO]
+    }
+}
+
+/// Change of the balance of the account consuming the notes of a [FillPlan].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BalanceDelta {
+    pub spent_faucet_id: AccountId,
+    pub spent: u64,
+    pub received_faucet_id: AccountId,
+    pub received: u64,
+}
+
+/// Resting orders consumed by a fill transaction, in order, with the amounts exchanged with each
+/// of them. Only the last order can be partially filled.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FillPlan {
+    pub fills: Vec<NoteFill>,
+}
+
+impl FillPlan {
+    /// Builds the plan of consuming `orders` with `args`, the fill amount of each order.
+    pub fn new(orders: &[Order], args: &[NoteArgs]) -> Result<Self, OrderError> {
+        if orders.len() != args.len() {
+            return Err(OrderError::InternalError(format!(
+                "Expected note args for {} orders, got {}",
+                orders.len(),
+                args.len()
+            )));
+        }
+
+        let fills = orders
+            .iter()
+            .zip(args)
+            .map(|(order, args)| NoteFill::new(*order, args[0].as_int()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(FillPlan { fills })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fills.is_empty()
+    }
+
+    pub fn orders(&self) -> Vec<Order> {
+        self.fills.iter().map(|fill| fill.order).collect()
+    }
+
+    pub fn args(&self) -> Vec<NoteArgs> {
+        self.fills.iter().map(NoteFill::args).collect()
+    }
+
+    /// Returns the total amount of the requested assets of the orders paid to their creators.
+    pub fn spent(&self) -> u64 {
+        self.fills.iter().map(|fill| fill.paid).sum()
+    }
+
+    /// Returns the fill of the order which is only partially filled, if any.
+    pub fn partial_fill(&self) -> Option<&NoteFill> {
+        self.fills.last().filter(|fill| fill.successor.is_some())
+    }
+
+    /// Returns the total amounts spent and received by the consumer of the notes, or `None` when
+    /// the plan is empty.
+    pub fn balance_delta(&self) -> Option<BalanceDelta> {
+        let first = self.fills.first()?;
+
+        Some(BalanceDelta {
+            spent_faucet_id: first.order.target_asset().faucet_id(),
+            spent: self.spent(),
+            received_faucet_id: first.order.source_asset().faucet_id(),
+            received: self.fills.iter().map(|fill| fill.received).sum(),
+        })
     }
 }
 
 // Conversions
 /////////////////////////////////////////////////
 
-impl From<InputNoteRecord> for Order {
-    fn from(value: InputNoteRecord) -> Self {
-        let (source_asset, target_asset) = get_assets_from_swap_note(&value);
-        let id = value.id();
-        Order {
-            id: Some(id),
-            source_asset,
-            target_asset,
-        }
+impl TryFrom<InputNoteRecord> for Order {
+    type Error = SwappNoteError;
+
+    fn try_from(value: InputNoteRecord) -> Result<Self, Self::Error> {
+        let metadata = value.metadata().ok_or(SwappNoteError::MissingMetadata)?;
+        let order = order_from_swapp_note(
+            value.id(),
+            value.details().script().hash(),
+            value.assets(),
+            value.details().inputs(),
+            metadata,
+        )?;
+        Ok(match value.inclusion_proof() {
+            Some(proof) => order.with_block_num(proof.location().block_num()),
+            None => order,
+        })
+    }
+}
+
+impl TryFrom<&Note> for Order {
+    type Error = SwappNoteError;
+
+    fn try_from(value: &Note) -> Result<Self, Self::Error> {
+        order_from_swapp_note(
+            value.id(),
+            value.recipient().script().hash(),
+            value.assets(),
+            value.recipient().inputs(),
+            value.metadata(),
+        )
+    }
+}
+
+/// Builds the order of a SWAPP note from its script root, assets, inputs and metadata.
+///
+/// Notes are rejected unless they run the SWAPP script, hold a single fungible asset and are
+/// tagged, both in their metadata and in their inputs, for the pair of their assets.
+fn order_from_swapp_note(
+    id: NoteId,
+    script_root: Digest,
+    assets: &NoteAssets,
+    inputs: &NoteInputs,
+    metadata: &NoteMetadata,
+) -> Result<Order, SwappNoteError> {
+    if script_root != swapp_script_root() {
+        return Err(SwappNoteError::NotSwappScript(script_root));
+    }
+
+    let inputs = SwappNoteInputs::decode(inputs)?;
+    if inputs.script_hash != script_root {
+        return Err(SwappNoteError::ScriptHashMismatch(inputs.script_hash));
+    }
+
+    if assets.num_assets() != 1 {
+        return Err(SwappNoteError::WrongNumberOfAssets(assets.num_assets()));
+    }
+    let source_asset = match assets.iter().next() {
+        Some(Asset::Fungible(asset)) => Asset::Fungible(*asset),
+        _ => return Err(SwappNoteError::NonFungibleAsset),
+    };
+    let target_asset = Asset::Fungible(inputs.requested_asset);
+
+    // the tag commits to the faucets of both assets, so a note tagged for another pair would be
+    // listed in the wrong book
+    let tag = build_swap_tag(metadata.note_type(), &source_asset, &target_asset)
+        .map_err(|_| SwappNoteError::TagMismatch(metadata.tag()))?;
+    if metadata.tag() != tag {
+        return Err(SwappNoteError::TagMismatch(metadata.tag()));
+    }
+    if inputs.swapp_tag != tag {
+        return Err(SwappNoteError::TagMismatch(inputs.swapp_tag));
     }
+
+    Ok(Order::new(Some(id), source_asset, target_asset)
+        .with_validity(inputs.validity)
+        .with_creator(inputs.creator))
 }
 
 // Utils
 /////////////////////////////////////////////////
 
-pub fn match_orders(incoming_order: Order, existing_order: Order) -> Result<Order, OrderError> {
+/// Matches the incoming order against an existing order, in a transaction executed against
+/// `block_num`.
+pub fn match_orders(
+    incoming_order: Order,
+    existing_order: Order,
+    block_num: u32,
+) -> Result<Order, OrderError> {
     // Orders match if:
     // - They have inversed source and target assets
-    // - Exisiting order price is lower than incoming order price
+    // - Exisiting order price is not higher than the incoming order price, both in source asset
+    //   of the incoming order per target asset
+    // - Existing order can be filled at the given block
+    // - Orders were not created by the same account
 
     // assets do not match
     if !(existing_order.source_asset.faucet_id() == incoming_order.target_asset.faucet_id()
@@ -68,30 +482,242 @@ pub fn match_orders(incoming_order: Order, existing_order: Order) -> Result<Orde
         return Err(OrderError::AssetsNotMatching);
     }
 
-    // existing order price is too high
-    if existing_order.price() > incoming_order.price() {
+    // existing order price is too high, the price of the incoming order is in the inverse units
+    let limit_price = incoming_order.price().inverse();
+    if existing_order.price() > limit_price {
         return Err(OrderError::PriceTooHigh(
-            incoming_order.price() as u64,
-            existing_order.price() as u64,
+            limit_price,
+            existing_order.price(),
+        ));
+    }
+
+    // existing order expired or can not be filled yet
+    if !existing_order.validity().is_active_at(block_num) {
+        return Err(OrderError::InactiveOrder(
+            existing_order.validity(),
+            block_num,
         ));
     }
 
+    // orders of the same account
+    if incoming_order.creator().is_some() && incoming_order.creator() == existing_order.creator() {
+        return Err(OrderError::SelfTrade(existing_order));
+    }
+
     Ok(existing_order)
 }
 
+/// Sorts orders by price-time priority: lowest price first, then earliest commit block, then note
+/// id. Orders whose note was not committed yet come after the other orders of their price.
 pub fn sort_orders(mut orders: Vec<Order>) -> Vec<Order> {
-    orders.sort_by(|a, b| {
-        let a_price = a.price();
-        let b_price = b.price();
-
-        a_price
-            .partial_cmp(&b_price)
-            .unwrap_or(std::cmp::Ordering::Equal)
+    orders.sort_by_key(|order| {
+        (
+            order.price(),
+            order.block_num().unwrap_or(u32::MAX),
+            order.id(),
+        )
     });
 
     orders
 }
 
+/// Walks the opposite side of the book from best to worst price and selects the orders that fill
+/// the incoming order. Orders which can not be filled at `block_num`, usually the synced block
+/// height, and orders created by the account of the incoming order are skipped, as well as orders
+/// which the SWAPP script can not partially fill with what is left of the incoming order.
+///
+/// Returns the plan of filling the selected orders and the amount of the incoming source asset
+/// that was not consumed.
+pub fn fill_order(
+    incoming_order: Order,
+    book: &OrderBook,
+    block_num: u32,
+) -> Result<(FillPlan, u64), OrderError> {
+    // Keep only orders that match incoming order
+    let mut matching_orders = Vec::new();
+    for order in book.resting_orders(&incoming_order) {
+        match match_orders(incoming_order, order, block_num) {
+            Ok(order) => matching_orders.push(order),
+            Err(_) => continue,
+        }
+    }
+
+    let mut remaining_source = incoming_order.source_asset().unwrap_fungible().amount();
+
+    let mut plan = FillPlan::default();
+    for order in matching_orders {
+        if remaining_source == 0 {
+            break;
+        }
+
+        let order_amount = order.target_asset().unwrap_fungible().amount();
+        let Ok(fill) = NoteFill::new(order, order_amount.min(remaining_source)) else {
+            continue;
+        };
+        remaining_source -= fill.paid;
+        plan.fills.push(fill);
+    }
+
+    if plan.is_empty() {
+        return Err(OrderError::FailedFill(incoming_order));
+    }
+
+    Ok((plan, remaining_source))
+}
+
+/// Returns the resting orders created by the account of the incoming order which it would match,
+/// per the self-trade prevention `mode`.
+///
+/// These orders are never filled by [fill_order]. They are returned with
+/// [SelfTradePrevention::CancelResting], to be reclaimed in the fill transaction, and make the
+/// incoming order fail with [OrderError::SelfTrade] with [SelfTradePrevention::CancelIncoming].
+pub fn prevent_self_trades(
+    incoming_order: Order,
+    book: &OrderBook,
+    block_num: u32,
+    mode: SelfTradePrevention,
+) -> Result<Vec<Order>, OrderError> {
+    let mut own_orders = Vec::new();
+    for order in book.resting_orders(&incoming_order) {
+        if let Err(OrderError::SelfTrade(order)) = match_orders(incoming_order, order, block_num) {
+            own_orders.push(order);
+        }
+    }
+
+    match mode {
+        SelfTradePrevention::Skip => Ok(Vec::new()),
+        SelfTradePrevention::CancelResting => Ok(own_orders),
+        SelfTradePrevention::CancelIncoming => match own_orders.first() {
+            Some(order) => Err(OrderError::SelfTrade(*order)),
+            None => Ok(Vec::new()),
+        },
+    }
+}
+
+/// Fills the incoming order against the book like [fill_order], with the semantics of
+/// `execution`.
+///
+/// Fill-or-kill orders fail with [OrderError::NotFullyFilled] unless their full source amount is
+/// matched. Post-only orders fail with [OrderError::WouldCross] when they match resting orders,
+/// and with [OrderError::FailedFill] otherwise, in which case they can be posted.
+pub fn execute_order(
+    incoming_order: Order,
+    book: &OrderBook,
+    block_num: u32,
+    execution: Execution,
+) -> Result<(FillPlan, u64), OrderError> {
+    let fill = fill_order(incoming_order, book, block_num);
+    let source_amount = incoming_order.source_asset().unwrap_fungible().amount();
+
+    match execution {
+        Execution::Limit => fill,
+        Execution::FillOrKill => match fill {
+            Ok((plan, remaining_source)) => {
+                let filled_amount = plan.spent();
+                if filled_amount < source_amount {
+                    return Err(OrderError::NotFullyFilled(filled_amount, source_amount));
+                }
+                Ok((plan, remaining_source))
+            }
+            Err(OrderError::FailedFill(_)) => Err(OrderError::NotFullyFilled(0, source_amount)),
+            Err(err) => Err(err),
+        },
+        Execution::PostOnly => match fill {
+            Ok((plan, _)) => Err(OrderError::WouldCross(plan.fills.len())),
+            Err(err) => Err(err),
+        },
+    }
+}
+
+/// Walks the book from best to worst price, consuming price levels until the amount of the market
+/// order is spent or received. Orders which can not be filled at `block_num` and orders created by
+/// the account executing the market order are skipped.
+///
+/// Returns the plan of filling the selected orders. Fails with
+/// [OrderError::ZeroAmount] when the order spends or receives nothing,
+/// [OrderError::InsufficientLiquidity] when the book can not fill the order, and with
+/// [OrderError::SlippageExceeded] when its average price is out of the bound of the order.
+pub fn fill_market_order(
+    market_order: MarketOrder,
+    book: &OrderBook,
+    block_num: u32,
+) -> Result<FillPlan, OrderError> {
+    let amount = match market_order.amount {
+        MarketAmount::Spend(amount) | MarketAmount::Receive(amount) => amount,
+    };
+    if amount == 0 {
+        return Err(OrderError::ZeroAmount);
+    }
+    let mut remaining = amount;
+
+    let mut plan = FillPlan::default();
+    for order in book.orders_offering(market_order.target_faucet_id) {
+        if remaining == 0 {
+            break;
+        }
+        if order.target_asset().faucet_id() != market_order.source_faucet_id
+            || !order.validity().is_active_at(block_num)
+            || (market_order.account_id.is_some() && order.creator() == market_order.account_id)
+        {
+            continue;
+        }
+
+        let offered_amount = order.source_asset().unwrap_fungible().amount();
+        let requested_amount = order.target_asset().unwrap_fungible().amount();
+        let fill_amount = match market_order.amount {
+            MarketAmount::Spend(_) => remaining.min(requested_amount),
+            MarketAmount::Receive(_) if offered_amount <= remaining => requested_amount,
+            MarketAmount::Receive(_) => get_fill_amount_for(order.price(), remaining),
+        };
+        // notes the script can not fill are skipped
+        let Ok(fill) = NoteFill::new(order, fill_amount) else {
+            continue;
+        };
+
+        remaining = match market_order.amount {
+            MarketAmount::Spend(_) => remaining - fill.paid,
+            MarketAmount::Receive(_) => remaining.saturating_sub(fill.received),
+        };
+        plan.fills.push(fill);
+    }
+
+    if remaining > 0 {
+        return Err(OrderError::InsufficientLiquidity(
+            amount - remaining,
+            amount,
+        ));
+    }
+
+    let (Some(best_fill), Some(delta)) = (plan.fills.first(), plan.balance_delta()) else {
+        return Err(OrderError::ZeroAmount);
+    };
+    market_order.guard.check(
+        best_fill.order.price(),
+        Price::new(delta.received, delta.spent),
+    )?;
+
+    Ok(plan)
+}
+
+/// Returns the smallest fill amount for which the SWAPP note of an order with `price` sends at
+/// least `amount` of its offered asset, or its whole requested amount when there is none.
+fn get_fill_amount_for(price: Price, amount: u64) -> u64 {
+    let (mut low, mut high) = (0, price.requested());
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if price
+            .partial_exchange(mid)
+            .is_some_and(|received| received >= amount)
+        {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+
+    high
+}
+
 // Tests
 /////////////////////////////////////////////////
 
@@ -101,9 +727,19 @@ mod tests {
         accounts::AccountId,
         assets::{Asset, FungibleAsset},
         notes::NoteId,
+        Felt, ZERO,
     };
 
-    use crate::{errors::OrderError, order::match_orders};
+    use crate::{
+        book::OrderBook,
+        errors::OrderError,
+        order::{
+            execute_order, fill_market_order, fill_order, match_orders, prevent_self_trades,
+            sort_orders, BalanceDelta, Execution, FillPlan, MarketAmount, MarketOrder, NoteFill,
+            OrderValidity, SelfTradePrevention, SlippageGuard, SuccessorNote,
+        },
+        price::Price,
+    };
 
     use super::Order;
 
@@ -114,9 +750,8 @@ mod tests {
         let target_faucet_id_hex = "0x2540b08edc3b087d";
         let target_faucet_id = AccountId::from_hex(target_faucet_id_hex).unwrap();
 
-        // mock note id
-        let note_id_hex = "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129";
-        let note_id = NoteId::try_from_hex(note_id_hex).unwrap();
+        // mock note ids
+        let note_id = |i: u8| NoteId::try_from_hex(&format!("0x{:064x}", i)).unwrap();
 
         // create assets
         let source_amount = 10;
@@ -127,15 +762,15 @@ mod tests {
             Asset::Fungible(FungibleAsset::new(target_faucet_id, target_amount).unwrap());
 
         // incoming order
-        let order = Order::new(Some(note_id), source_asset, target_asset);
+        let order = Order::new(None, source_asset, target_asset);
 
         // existing orders
 
         // Full swap
-        let order1 = Order::new(Some(note_id), target_asset, source_asset);
+        let order1 = Order::new(Some(note_id(1)), target_asset, source_asset);
 
         // Assets do not match
-        let order2 = Order::new(Some(note_id), source_asset, source_asset);
+        let order2 = Order::new(Some(note_id(2)), source_asset, source_asset);
 
         // Price is too high
         let new_source_amount = 30;
@@ -144,22 +779,36 @@ mod tests {
             Asset::Fungible(FungibleAsset::new(source_faucet_id, new_source_amount).unwrap());
         let new_target_asset =
             Asset::Fungible(FungibleAsset::new(target_faucet_id, new_target_amount).unwrap());
-        let order3 = Order::new(Some(note_id), new_target_asset, new_source_asset);
+        let order3 = Order::new(Some(note_id(3)), new_target_asset, new_source_asset);
 
-        // Partial swap
+        // Price is too high for the incoming order, but not for market orders
         let new_source_amount = 10;
         let new_target_amount = 5;
         let new_source_asset =
             Asset::Fungible(FungibleAsset::new(source_faucet_id, new_source_amount).unwrap());
         let new_target_asset =
             Asset::Fungible(FungibleAsset::new(target_faucet_id, new_target_amount).unwrap());
-        let order4 = Order::new(Some(note_id), new_target_asset, new_source_asset);
+        let order4 = Order::new(Some(note_id(4)), new_target_asset, new_source_asset);
 
         let orders = vec![order1, order2, order3, order4];
 
         (order, orders)
     }
 
+    /// Returns an order requesting twice what `incoming_order` offers at its limit price, after
+    /// the orders of [build_orders] in time priority.
+    fn build_partial_order(incoming_order: Order) -> Order {
+        let note_id = NoteId::try_from_hex(&format!("0x{:064x}", 5)).unwrap();
+        let source_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.target_asset().faucet_id(), 40).unwrap(),
+        );
+        let target_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.source_asset().faucet_id(), 20).unwrap(),
+        );
+
+        Order::new(Some(note_id), source_asset, target_asset)
+    }
+
     #[test]
     fn order_matching_succeeds() {
         let (incoming_order, existing_orders) = build_orders();
@@ -167,19 +816,463 @@ mod tests {
             Ok(existing_orders[0]),
             Err(OrderError::AssetsNotMatching),
             Err(OrderError::PriceTooHigh(
-                incoming_order.price() as u64,
-                existing_orders[2].price() as u64,
+                incoming_order.price().inverse(),
+                existing_orders[2].price(),
+            )),
+            Err(OrderError::PriceTooHigh(
+                incoming_order.price().inverse(),
+                existing_orders[3].price(),
             )),
-            Ok(existing_orders[3]),
         ];
 
         for (existing_order, expected_result) in existing_orders.into_iter().zip(expected_results) {
             assert_eq!(
-                match_orders(incoming_order, existing_order),
+                match_orders(incoming_order, existing_order, 0),
                 expected_result,
                 "Mismatch for order: {:?}",
                 existing_order
             );
         }
     }
+
+    fn build_book(incoming_order: Order, existing_orders: &[Order]) -> OrderBook {
+        OrderBook::from_orders(
+            incoming_order.target_asset().faucet_id(),
+            incoming_order.source_asset().faucet_id(),
+            existing_orders.iter().copied(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn order_filling_succeeds() {
+        let (incoming_order, existing_orders) = build_orders();
+        let book = build_book(incoming_order, &existing_orders);
+
+        // order1 has the best price and requests exactly what the incoming order offers
+        let (plan, remaining_source) = fill_order(incoming_order, &book, 0).unwrap();
+
+        assert_eq!(plan.orders(), vec![existing_orders[0]]);
+        assert_eq!(remaining_source, 0);
+        assert_eq!(plan.args(), vec![[Felt::new(10), ZERO, ZERO, ZERO]]);
+        assert_eq!(plan.partial_fill(), None);
+
+        // offering only half of it leaves order1 partially filled
+        let source_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.source_asset().faucet_id(), 5).unwrap(),
+        );
+        let target_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.target_asset().faucet_id(), 10).unwrap(),
+        );
+        let incoming_order = Order::new(None, source_asset, target_asset);
+
+        let (plan, remaining_source) = fill_order(incoming_order, &book, 0).unwrap();
+
+        assert_eq!(plan.orders(), vec![existing_orders[0]]);
+        assert_eq!(remaining_source, 0);
+        assert_eq!(plan.args(), vec![[Felt::new(5), ZERO, ZERO, ZERO]]);
+        assert_eq!(plan.partial_fill().map(|fill| fill.paid), Some(5));
+    }
+
+    #[test]
+    fn fill_plan_follows_swapp_math() {
+        let (incoming_order, existing_orders) = build_orders();
+        let (order1, order4) = (existing_orders[0], existing_orders[3]);
+        let args = |fill_amount| [Felt::new(fill_amount), ZERO, ZERO, ZERO];
+
+        // order1 is fully filled, order4 offers 5 for 10 and is filled with 3, for which the
+        // script sends 1 rather than 1.5
+        let plan = FillPlan::new(&[order1, order4], &[args(10), args(3)]).unwrap();
+        assert_eq!((plan.fills[0].paid, plan.fills[0].received), (10, 20));
+        assert_eq!(plan.fills[0].successor, None);
+        assert_eq!((plan.fills[1].paid, plan.fills[1].received), (3, 1));
+        assert_eq!(plan.args(), vec![args(10), args(3)]);
+
+        let partial_fill = plan.partial_fill().unwrap();
+        assert_eq!(partial_fill.order, order4);
+        assert_eq!(
+            partial_fill.successor,
+            Some(SuccessorNote {
+                parent_id: order4.id().unwrap(),
+                offered_asset: FungibleAsset::new(incoming_order.target_asset().faucet_id(), 4)
+                    .unwrap(),
+                requested_asset: FungibleAsset::new(incoming_order.source_asset().faucet_id(), 7)
+                    .unwrap(),
+            })
+        );
+
+        assert_eq!(
+            plan.balance_delta(),
+            Some(BalanceDelta {
+                spent_faucet_id: incoming_order.source_asset().faucet_id(),
+                spent: 13,
+                received_faucet_id: incoming_order.target_asset().faucet_id(),
+                received: 21,
+            })
+        );
+
+        assert_eq!(
+            FillPlan::new(&[order1], &[args(11)]),
+            Err(OrderError::InvalidFillAmount(11, 10))
+        );
+        assert_eq!(FillPlan::default().balance_delta(), None);
+    }
+
+    /// Returns a resting order with note id `i` offering `offered` of the target asset of
+    /// `incoming_order` for `requested` of its source asset.
+    fn build_resting_order(incoming_order: Order, i: u8, offered: u64, requested: u64) -> Order {
+        let note_id = NoteId::try_from_hex(&format!("0x{:064x}", i)).unwrap();
+        let source_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.target_asset().faucet_id(), offered).unwrap(),
+        );
+        let target_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.source_asset().faucet_id(), requested).unwrap(),
+        );
+
+        Order::new(Some(note_id), source_asset, target_asset)
+    }
+
+    /// Returns an order offering `offered` of the source asset of `incoming_order` for
+    /// `requested` of its target asset.
+    fn resize(incoming_order: Order, offered: u64, requested: u64) -> Order {
+        let source_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.source_asset().faucet_id(), offered).unwrap(),
+        );
+        let target_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.target_asset().faucet_id(), requested).unwrap(),
+        );
+
+        Order::new(None, source_asset, target_asset)
+    }
+
+    #[test]
+    fn partial_fills_receiving_nothing_are_skipped() {
+        let (incoming_order, _) = build_orders();
+        let first = build_resting_order(incoming_order, 6, 10, 1000);
+        let second = build_resting_order(incoming_order, 7, 10, 1000);
+
+        // 50 of the 1000 requested is worth 0.5, which the script rounds down to 0
+        assert_eq!(
+            NoteFill::new(second, 50),
+            Err(OrderError::InvalidFillAmount(50, 1000))
+        );
+
+        // the 50 left after filling the first order are not spent on the second one
+        let incoming_order = resize(incoming_order, 1050, 10);
+        let book = build_book(incoming_order, &[first, second]);
+        let (plan, remaining_source) = fill_order(incoming_order, &book, 0).unwrap();
+        assert_eq!(plan.orders(), vec![first]);
+        assert_eq!(remaining_source, 50);
+    }
+
+    #[test]
+    fn partial_fills_receiving_everything_are_skipped() {
+        let (incoming_order, _) = build_orders();
+        let first = build_resting_order(incoming_order, 6, 200_000, 200_001);
+        let second = build_resting_order(incoming_order, 7, 200_000, 200_001);
+
+        // the ratio of 200001 / 200000 is truncated to 1, so 200000 of the 200001 requested
+        // receive all of the offered asset and would leave an empty successor
+        assert_eq!(
+            NoteFill::new(second, 200_000),
+            Err(OrderError::InvalidFillAmount(200_000, 200_001))
+        );
+
+        let incoming_order = resize(incoming_order, 400_001, 399_999);
+        let book = build_book(incoming_order, &[first, second]);
+        let (plan, remaining_source) = fill_order(incoming_order, &book, 0).unwrap();
+        assert_eq!(plan.orders(), vec![first]);
+        assert_eq!(remaining_source, 200_000);
+    }
+
+    #[test]
+    fn unfilled_remainder_rests_at_the_same_price() {
+        let (incoming_order, existing_orders) = build_orders();
+
+        // order1 takes half of a twice larger order
+        let source_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.source_asset().faucet_id(), 20).unwrap(),
+        );
+        let target_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.target_asset().faucet_id(), 40).unwrap(),
+        );
+        let validity = OrderValidity::new(None, Some(100));
+        let larger_order = Order::new(None, source_asset, target_asset).with_validity(validity);
+        let book = build_book(larger_order, &[existing_orders[0]]);
+
+        let (plan, remaining_source) = fill_order(larger_order, &book, 0).unwrap();
+        assert_eq!(remaining_source, 10);
+        assert_eq!(plan.partial_fill(), None);
+
+        let remainder = larger_order.remainder(remaining_source).unwrap();
+        assert_eq!(remainder.source_asset().unwrap_fungible().amount(), 10);
+        assert_eq!(remainder.target_asset().unwrap_fungible().amount(), 20);
+        assert_eq!(remainder.validity(), validity);
+
+        // the requested amount is rounded up
+        let source_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.source_asset().faucet_id(), 3).unwrap(),
+        );
+        let target_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.target_asset().faucet_id(), 10).unwrap(),
+        );
+        let order = Order::new(None, source_asset, target_asset);
+        let remainder = order.remainder(2).unwrap();
+        assert_eq!(remainder.target_asset().unwrap_fungible().amount(), 7);
+        assert!(remainder.price() >= order.price());
+
+        assert_eq!(larger_order.remainder(0), None);
+        assert_eq!(larger_order.remainder(21), None);
+    }
+
+    #[test]
+    fn orders_are_sorted_by_price_time_priority() {
+        let (_, existing_orders) = build_orders();
+        let order1 = existing_orders[0];
+        let same_price = |order: Order, block_num| {
+            Order::new(order.id(), order1.source_asset(), order1.target_asset())
+                .with_block_num(block_num)
+        };
+
+        // same price, same block, ordered by note id
+        let (first_in_block, second_in_block) = {
+            let (a, b) = (same_price(order1, 3), same_price(existing_orders[3], 3));
+            if a.id() < b.id() {
+                (a, b)
+            } else {
+                (b, a)
+            }
+        };
+        // same price, earlier block
+        let earlier = same_price(existing_orders[1], 2);
+        // higher price, earliest block
+        let higher_price = existing_orders[2].with_block_num(1);
+
+        assert_eq!(
+            sort_orders(vec![higher_price, second_in_block, first_in_block, earlier]),
+            vec![earlier, first_in_block, second_in_block, higher_price]
+        );
+    }
+
+    #[test]
+    fn order_filling_fails_without_matching_orders() {
+        let (incoming_order, existing_orders) = build_orders();
+        let book = build_book(incoming_order, &[existing_orders[1], existing_orders[2]]);
+
+        assert_eq!(
+            fill_order(incoming_order, &book, 0),
+            Err(OrderError::FailedFill(incoming_order))
+        );
+    }
+
+    #[test]
+    fn inactive_orders_are_not_filled() {
+        let (incoming_order, existing_orders) = build_orders();
+        let validity = OrderValidity::new(Some(5), Some(10));
+        let order1 = existing_orders[0].with_validity(validity);
+
+        assert!(!validity.is_active_at(4));
+        assert!(validity.is_active_at(5));
+        assert!(validity.is_active_at(10));
+        assert!(validity.is_expired_at(11));
+
+        assert_eq!(
+            match_orders(incoming_order, order1, 11),
+            Err(OrderError::InactiveOrder(validity, 11))
+        );
+
+        // the partial order is filled instead of order1 before and after its validity
+        let partial_order = build_partial_order(incoming_order);
+        let book = build_book(incoming_order, &[order1, partial_order]);
+        for block_num in [4, 11] {
+            let (plan, _) = fill_order(incoming_order, &book, block_num).unwrap();
+            assert_eq!(plan.orders(), vec![partial_order]);
+        }
+
+        let (plan, _) = fill_order(incoming_order, &book, 7).unwrap();
+        assert_eq!(plan.fills[0].order, order1);
+    }
+
+    #[test]
+    fn fill_or_kill_orders_are_filled_entirely_or_not_at_all() {
+        let (incoming_order, existing_orders) = build_orders();
+
+        // order1 matches the full source amount
+        let book = build_book(incoming_order, &existing_orders);
+        assert_eq!(
+            execute_order(incoming_order, &book, 0, Execution::FillOrKill),
+            fill_order(incoming_order, &book, 0)
+        );
+
+        // order1 only requests half of a twice larger order
+        let source_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.source_asset().faucet_id(), 20).unwrap(),
+        );
+        let target_asset = Asset::Fungible(
+            FungibleAsset::new(incoming_order.target_asset().faucet_id(), 40).unwrap(),
+        );
+        let larger_order = Order::new(None, source_asset, target_asset);
+        let book = build_book(larger_order, &[existing_orders[0]]);
+        assert_eq!(
+            execute_order(larger_order, &book, 0, Execution::FillOrKill),
+            Err(OrderError::NotFullyFilled(10, 20))
+        );
+
+        // nothing matches
+        let book = build_book(incoming_order, &[existing_orders[2]]);
+        assert_eq!(
+            execute_order(incoming_order, &book, 0, Execution::FillOrKill),
+            Err(OrderError::NotFullyFilled(0, 10))
+        );
+    }
+
+    #[test]
+    fn post_only_orders_never_take_liquidity() {
+        let (incoming_order, existing_orders) = build_orders();
+
+        let book = build_book(incoming_order, &existing_orders);
+        assert_eq!(
+            execute_order(incoming_order, &book, 0, Execution::PostOnly),
+            Err(OrderError::WouldCross(1))
+        );
+
+        // the order does not cross the book and can be posted
+        let book = build_book(incoming_order, &[existing_orders[2]]);
+        assert_eq!(
+            execute_order(incoming_order, &book, 0, Execution::PostOnly),
+            Err(OrderError::FailedFill(incoming_order))
+        );
+    }
+
+    #[test]
+    fn self_trades_are_prevented() {
+        let (incoming_order, existing_orders) = build_orders();
+        let account_id = AccountId::from_hex("0x9b6a0ea2ad6e5b3a").unwrap();
+        let incoming_order = incoming_order.with_creator(account_id);
+        let order1 = existing_orders[0].with_creator(account_id);
+
+        assert_eq!(
+            match_orders(incoming_order, order1, 0),
+            Err(OrderError::SelfTrade(order1))
+        );
+
+        // the partial order is filled instead of order1, whatever the mode
+        let partial_order = build_partial_order(incoming_order);
+        let book = build_book(incoming_order, &[order1, partial_order]);
+        let (plan, _) = fill_order(incoming_order, &book, 0).unwrap();
+        assert_eq!(plan.orders(), vec![partial_order]);
+
+        assert_eq!(
+            prevent_self_trades(incoming_order, &book, 0, SelfTradePrevention::Skip),
+            Ok(vec![])
+        );
+        assert_eq!(
+            prevent_self_trades(incoming_order, &book, 0, SelfTradePrevention::CancelResting),
+            Ok(vec![order1])
+        );
+        assert_eq!(
+            prevent_self_trades(
+                incoming_order,
+                &book,
+                0,
+                SelfTradePrevention::CancelIncoming
+            ),
+            Err(OrderError::SelfTrade(order1))
+        );
+
+        // market orders skip the orders of their account too
+        let market_order = MarketOrder {
+            source_faucet_id: incoming_order.source_asset().faucet_id(),
+            target_faucet_id: incoming_order.target_asset().faucet_id(),
+            amount: MarketAmount::Spend(10),
+            guard: SlippageGuard::MaxSlippageBps(u64::MAX),
+            account_id: Some(account_id),
+        };
+        let plan = fill_market_order(market_order, &book, 0).unwrap();
+        assert_eq!(plan.orders(), vec![partial_order]);
+    }
+
+    #[test]
+    fn market_orders_walk_the_book_within_their_slippage_bound() {
+        let (incoming_order, existing_orders) = build_orders();
+        let book = build_book(incoming_order, &existing_orders);
+        let market_order = |amount, guard| MarketOrder {
+            source_faucet_id: incoming_order.source_asset().faucet_id(),
+            target_faucet_id: incoming_order.target_asset().faucet_id(),
+            amount,
+            guard,
+            account_id: None,
+        };
+        let no_bound = SlippageGuard::MaxSlippageBps(u64::MAX);
+
+        // order1 then order4 are consumed entirely, for an average price of 20 / 25
+        let plan = fill_market_order(
+            market_order(
+                MarketAmount::Receive(25),
+                SlippageGuard::MaxAveragePrice(Price::new(1, 1)),
+            ),
+            &book,
+            0,
+        )
+        .unwrap();
+        assert_eq!(plan.orders(), vec![existing_orders[0], existing_orders[3]]);
+        assert_eq!(
+            plan.args(),
+            vec![
+                [Felt::new(10), ZERO, ZERO, ZERO],
+                [Felt::new(10), ZERO, ZERO, ZERO]
+            ]
+        );
+
+        // the best price is 10 / 20, the average price is 60% above it
+        assert_eq!(
+            fill_market_order(
+                market_order(
+                    MarketAmount::Receive(25),
+                    SlippageGuard::MaxSlippageBps(5999)
+                ),
+                &book,
+                0,
+            ),
+            Err(OrderError::SlippageExceeded(
+                Price::new(25, 20),
+                SlippageGuard::MaxSlippageBps(5999)
+            ))
+        );
+        assert!(fill_market_order(
+            market_order(
+                MarketAmount::Receive(25),
+                SlippageGuard::MaxSlippageBps(6000)
+            ),
+            &book,
+            0,
+        )
+        .is_ok());
+
+        // order4 is partially filled with the smallest amount sending what is left to receive
+        let plan =
+            fill_market_order(market_order(MarketAmount::Receive(22), no_bound), &book, 0).unwrap();
+        assert_eq!(plan.fills[1].paid, 4);
+
+        // order4 is partially filled with what is left to spend
+        let plan =
+            fill_market_order(market_order(MarketAmount::Spend(15), no_bound), &book, 0).unwrap();
+        assert_eq!(plan.fills[1].paid, 5);
+
+        // the whole book only offers 35
+        assert_eq!(
+            fill_market_order(market_order(MarketAmount::Receive(40), no_bound), &book, 0),
+            Err(OrderError::InsufficientLiquidity(35, 40))
+        );
+
+        // empty market orders are rejected instead of filling nothing
+        assert_eq!(
+            fill_market_order(market_order(MarketAmount::Spend(0), no_bound), &book, 0),
+            Err(OrderError::ZeroAmount)
+        );
+        assert_eq!(
+            fill_market_order(market_order(MarketAmount::Receive(0), no_bound), &book, 0),
+            Err(OrderError::ZeroAmount)
+        );
+    }
 }
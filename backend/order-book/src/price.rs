@@ -0,0 +1,207 @@
+use std::{cmp::Ordering, fmt};
+
+use miden_client::Felt;
+
+// Price
+// ================================================================================================
+
+/// Exact price of an order, in requested asset per offered asset.
+///
+/// The price keeps the raw amounts of the order rather than a reduced fraction or a float: prices
+/// are compared exactly by cross-multiplication, and partial fills are computed with the same
+/// fixed-point arithmetic as `calculate_partial_exchange` in `SWAPP.masm`, which depends on the
+/// raw amounts.
+///
+/// An order offering nothing has an infinite price, higher than any other price.
+#[derive(Debug, Clone, Copy)]
+pub struct Price {
+    offered: u64,
+    requested: u64,
+}
+
+impl Price {
+    /// Scaling factor of the fixed-point arithmetic of `SWAPP.masm`.
+    pub const FACTOR: u64 = 100_000;
+
+    /// Default number of decimals of the displayed price, matching [Price::FACTOR].
+    const DECIMALS: usize = 5;
+
+    /// Maximum number of decimals of the displayed price, so that the scaled amount fits a u128.
+    const MAX_DECIMALS: usize = 18;
+
+    pub fn new(offered: u64, requested: u64) -> Self {
+        Price { offered, requested }
+    }
+
+    pub fn offered(&self) -> u64 {
+        self.offered
+    }
+
+    pub fn requested(&self) -> u64 {
+        self.requested
+    }
+
+    /// Returns the price in offered asset per requested asset.
+    pub fn inverse(&self) -> Price {
+        Price {
+            offered: self.requested,
+            requested: self.offered,
+        }
+    }
+
+    /// Returns true if this price is at most `bps` basis points higher than `other`.
+    pub fn is_within_bps_of(&self, other: &Price, bps: u64) -> bool {
+        if self.offered == 0 || other.offered == 0 {
+            return self <= other;
+        }
+
+        let price = self.requested as u128 * other.offered as u128;
+        let reference = other.requested as u128 * self.offered as u128;
+        let slippage = (reference / BPS)
+            .saturating_mul(bps as u128)
+            .saturating_add((reference % BPS) * bps as u128 / BPS);
+
+        price <= reference.saturating_add(slippage)
+    }
+
+    /// Returns the amount of the offered asset sent to the consumer of a SWAPP note offering
+    /// `offered` for `requested`, when `fill_amount` of the requested asset is provided.
+    ///
+    /// This mirrors `calculate_partial_exchange` in `SWAPP.masm` step by step, including its
+    /// wrapping multiplications, truncating divisions and reductions modulo the field. Returns
+    /// `None` where the script would fail on a division by zero.
+    pub fn partial_exchange(&self, fill_amount: u64) -> Option<u64> {
+        let (offered, requested) = (self.offered, self.requested);
+
+        let amount = if requested > offered {
+            let ratio = to_felt(requested.wrapping_mul(Self::FACTOR).checked_div(offered)?);
+            fill_amount.wrapping_mul(Self::FACTOR).checked_div(ratio)?
+        } else {
+            let ratio = offered.wrapping_mul(Self::FACTOR).checked_div(requested)?;
+            ratio.wrapping_mul(fill_amount).checked_div(Self::FACTOR)?
+        };
+
+        Some(to_felt(amount))
+    }
+}
+
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.offered, other.offered) {
+            (0, 0) => Ordering::Equal,
+            (0, _) => Ordering::Greater,
+            (_, 0) => Ordering::Less,
+            _ => {
+                let lhs = self.requested as u128 * other.offered as u128;
+                let rhs = other.requested as u128 * self.offered as u128;
+                lhs.cmp(&rhs)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Price {
+    /// Writes the price as a decimal truncated to the requested precision, 5 decimals by default.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimals = f
+            .precision()
+            .unwrap_or(Self::DECIMALS)
+            .min(Self::MAX_DECIMALS);
+
+        let price = if self.offered == 0 {
+            "inf".to_string()
+        } else if decimals == 0 {
+            (self.requested as u128 / self.offered as u128).to_string()
+        } else {
+            let scale = 10u128.pow(decimals as u32);
+            let scaled = self.requested as u128 * scale / self.offered as u128;
+            format!(
+                "{}.{:0width$}",
+                scaled / scale,
+                scaled % scale,
+                width = decimals
+            )
+        };
+
+        // `pad` would truncate the price to the precision, so only the width is applied
+        let width = f.width().unwrap_or(0);
+        match f.align() {
+            Some(fmt::Alignment::Left) => write!(f, "{:<width$}", price),
+            Some(fmt::Alignment::Center) => write!(f, "{:^width$}", price),
+            _ => write!(f, "{:>width$}", price),
+        }
+    }
+}
+
+// Helpers
+// ================================================================================================
+
+/// Number of basis points in one.
+const BPS: u128 = 10_000;
+
+/// Reduces a value modulo the field, as happens to every value the script keeps on the stack or in
+/// memory as a single element.
+fn to_felt(value: u64) -> u64 {
+    Felt::new(value).into()
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::Price;
+
+    #[test]
+    fn prices_are_compared_exactly() {
+        assert_eq!(Price::new(10, 20), Price::new(5, 10));
+        assert!(Price::new(3, 1) < Price::new(2, 1));
+        assert!(Price::new(u64::MAX - 1, u64::MAX) > Price::new(u64::MAX, u64::MAX - 1));
+        assert!(Price::new(0, 1) > Price::new(1, u64::MAX));
+        assert_eq!(Price::new(2, 1).inverse(), Price::new(1, 2));
+    }
+
+    #[test]
+    fn slippage_is_measured_in_basis_points() {
+        let best = Price::new(100, 200);
+        assert!(Price::new(100, 202).is_within_bps_of(&best, 100));
+        assert!(!Price::new(100, 203).is_within_bps_of(&best, 100));
+        assert!(Price::new(100, 190).is_within_bps_of(&best, 0));
+        assert!(!Price::new(100, 201).is_within_bps_of(&best, 0));
+        assert!(Price::new(u64::MAX, u64::MAX).is_within_bps_of(&Price::new(1, 1), 0));
+        assert!(!Price::new(0, 1).is_within_bps_of(&best, u64::MAX));
+    }
+
+    #[test]
+    fn partial_exchange_mirrors_swapp_script() {
+        // requested > offered: ratio = 3 * 1e5 / 2 = 150000, out = 7 * 1e5 / 150000 = 4
+        assert_eq!(Price::new(2, 3).partial_exchange(7), Some(4));
+        // requested <= offered: ratio = 1000 * 1e5 / 3 = 33333333, out = 33333333 * 1 / 1e5 = 333
+        assert_eq!(Price::new(1000, 3).partial_exchange(1), Some(333));
+        // an exact ratio fills proportionally
+        assert_eq!(Price::new(10, 20).partial_exchange(10), Some(5));
+        assert_eq!(Price::new(0, 20).partial_exchange(10), None);
+    }
+
+    #[test]
+    fn prices_are_displayed_as_truncated_decimals() {
+        assert_eq!(Price::new(3, 1).to_string(), "0.33333");
+        assert_eq!(format!("{:.2}", Price::new(1, 2)), "2.00");
+        assert_eq!(format!("{:<6.0}|", Price::new(2, 5)), "2     |");
+        assert_eq!(Price::new(0, 5).to_string(), "inf");
+    }
+}
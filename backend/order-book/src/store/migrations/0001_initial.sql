@@ -0,0 +1,31 @@
+-- Orders backed by a SWAPP note, along with the note they were partially filled from.
+CREATE TABLE orders (
+    note_id TEXT NOT NULL PRIMARY KEY,
+    tag INTEGER NOT NULL,
+    source_faucet_id TEXT NOT NULL,
+    source_amount INTEGER NOT NULL,
+    target_faucet_id TEXT NOT NULL,
+    target_amount INTEGER NOT NULL,
+    parent_note_id TEXT REFERENCES orders (note_id),
+    status TEXT NOT NULL,
+    created_block INTEGER NOT NULL
+);
+
+CREATE INDEX orders_tag_status ON orders (tag, status);
+
+-- Fills of an order, `successor_note_id` is the SWAPP note holding the remainder of a partial fill.
+CREATE TABLE fills (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    note_id TEXT NOT NULL REFERENCES orders (note_id),
+    fill_amount INTEGER NOT NULL,
+    successor_note_id TEXT REFERENCES orders (note_id),
+    block_num INTEGER NOT NULL
+);
+
+-- Every status an order went through.
+CREATE TABLE status_transitions (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    note_id TEXT NOT NULL REFERENCES orders (note_id),
+    status TEXT NOT NULL,
+    block_num INTEGER NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Block in which the SWAPP note of an order was committed, used for price-time priority. It is
+-- unknown for the remainder of a partial fill until its note is synced.
+ALTER TABLE orders ADD COLUMN commit_block INTEGER;
@@ -0,0 +1,5 @@
+-- Account which created an order, used to prevent self-trades, and the range of blocks in which
+-- its SWAPP note can be filled. They are unknown for orders stored before this migration.
+ALTER TABLE orders ADD COLUMN creator_id TEXT;
+ALTER TABLE orders ADD COLUMN good_after_block INTEGER;
+ALTER TABLE orders ADD COLUMN expiry_block INTEGER;
@@ -0,0 +1,14 @@
+-- Tags of the unspent input notes of the client, so that notes can be looked up by tag without
+-- scanning the client store. It is updated with the notes of every sync.
+CREATE TABLE note_tags (
+    note_id TEXT NOT NULL PRIMARY KEY,
+    tag INTEGER NOT NULL
+);
+
+CREATE INDEX note_tags_tag ON note_tags (tag);
+
+-- Block the client was synced to when the note index was last updated, a single row.
+CREATE TABLE note_index (
+    id INTEGER NOT NULL PRIMARY KEY CHECK (id = 0),
+    block_num INTEGER NOT NULL
+);
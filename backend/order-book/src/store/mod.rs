@@ -0,0 +1,768 @@
+use std::{fmt, str::FromStr};
+
+use miden_client::{
+    accounts::AccountId,
+    assets::{Asset, FungibleAsset},
+    notes::{build_swap_tag, NoteId, NoteTag, NoteType},
+};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+
+use crate::{
+    errors::StoreError,
+    order::{FillPlan, Order, OrderValidity},
+};
+
+/// Migrations applied in order, the schema version is tracked with `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    include_str!("migrations/0001_initial.sql"),
+    include_str!("migrations/0002_commit_block.sql"),
+    include_str!("migrations/0003_order_creator.sql"),
+    include_str!("migrations/0004_note_tags.sql"),
+];
+
+// Order Status
+// ================================================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    /// The SWAPP note is unspent and can be filled.
+    Open,
+    /// The SWAPP note was consumed by a partial fill, the remainder lives in a successor note.
+    PartiallyFilled,
+    /// The SWAPP note was consumed by a full fill.
+    Filled,
+    /// The SWAPP note was reclaimed by its creator.
+    Cancelled,
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderStatus::Open => write!(f, "open"),
+            OrderStatus::PartiallyFilled => write!(f, "partially_filled"),
+            OrderStatus::Filled => write!(f, "filled"),
+            OrderStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl FromStr for OrderStatus {
+    type Err = StoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(OrderStatus::Open),
+            "partially_filled" => Ok(OrderStatus::PartiallyFilled),
+            "filled" => Ok(OrderStatus::Filled),
+            "cancelled" => Ok(OrderStatus::Cancelled),
+            _ => Err(StoreError::ParsingError(format!(
+                "Unknown order status {}",
+                s
+            ))),
+        }
+    }
+}
+
+// Records
+// ================================================================================================
+
+/// Order as stored in the order book store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderRecord {
+    pub order: Order,
+    pub tag: NoteTag,
+    /// SWAPP note this order is the remainder of, if it was created by a partial fill.
+    pub parent_note_id: Option<NoteId>,
+    pub status: OrderStatus,
+    pub created_block: u32,
+}
+
+/// Fill of a stored order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FillRecord {
+    pub note_id: NoteId,
+    /// Amount of the requested asset sent to the SWAPP note.
+    pub fill_amount: u64,
+    /// SWAPP note created for the remainder of a partial fill.
+    pub successor_note_id: Option<NoteId>,
+    pub block_num: u32,
+}
+
+// Order Book Store
+// ================================================================================================
+
+/// SQLite store owned by the order book, independent from the miden-client store.
+pub struct OrderBookStore {
+    conn: Connection,
+}
+
+impl OrderBookStore {
+    /// Opens the store at `path`, creating it and applying pending migrations if needed.
+    pub fn open(path: &str) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Opens a store which only lives in memory.
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> Result<Self, StoreError> {
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let tx = conn.transaction()?;
+        for migration in MIGRATIONS.iter().skip(version as usize) {
+            tx.execute_batch(migration)?;
+        }
+        tx.pragma_update(None, "user_version", MIGRATIONS.len() as i64)?;
+        tx.commit()?;
+
+        Ok(Self { conn })
+    }
+
+    /// Inserts a new open order, orders which are already stored are left untouched apart from
+    /// their commit block, which is set once known.
+    pub fn insert_order(
+        &mut self,
+        order: Order,
+        parent_note_id: Option<NoteId>,
+        block_num: u32,
+    ) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+        insert_order(&tx, order, parent_note_id, block_num)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Records a fill of the order backed by `note_id`.
+    ///
+    /// When `successor` is set the order was partially filled and `successor` is the order for its
+    /// remainder, which is stored as a new open order.
+    pub fn record_fill(
+        &mut self,
+        note_id: NoteId,
+        fill_amount: u64,
+        successor: Option<Order>,
+        block_num: u32,
+    ) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+        record_fill(&tx, note_id, fill_amount, successor, block_num)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Records the fills of a transaction executing `plan`.
+    ///
+    /// Orders which are not stored yet are inserted first. When the last order of the plan is only
+    /// partially filled, `successor` is the order of its successor note, which is stored as a new
+    /// open order.
+    pub fn record_fills(
+        &mut self,
+        plan: &FillPlan,
+        successor: Option<Order>,
+        block_num: u32,
+    ) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+
+        for fill in plan.fills.iter() {
+            let note_id = fill.order.id().ok_or(StoreError::ParsingError(
+                "Only orders backed by a note can be filled".to_string(),
+            ))?;
+            let successor = match fill.successor {
+                Some(successor_note) if successor_note.parent_id == note_id => successor,
+                _ => None,
+            };
+
+            insert_order(&tx, fill.order, None, block_num)?;
+            record_fill(&tx, note_id, fill.paid, successor, block_num)?;
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Records that the creator of `orders` reclaimed their SWAPP notes.
+    ///
+    /// Orders which are not stored yet are inserted first.
+    pub fn record_cancels(&mut self, orders: &[Order], block_num: u32) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+
+        for order in orders {
+            let note_id = order.id().ok_or(StoreError::ParsingError(
+                "Only orders backed by a note can be cancelled".to_string(),
+            ))?;
+
+            insert_order(&tx, *order, None, block_num)?;
+            update_status(&tx, note_id, OrderStatus::Cancelled, block_num)?;
+            remove_indexed_note(&tx, note_id)?;
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Moves the order backed by `note_id` to `status`.
+    pub fn update_status(
+        &mut self,
+        note_id: NoteId,
+        status: OrderStatus,
+        block_num: u32,
+    ) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+        update_status(&tx, note_id, status, block_num)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    pub fn get_order(&self, note_id: NoteId) -> Result<Option<OrderRecord>, StoreError> {
+        self.conn
+            .query_row(
+                "SELECT * FROM orders WHERE note_id = ?1",
+                params![note_id.to_hex()],
+                RawOrderRecord::from_row,
+            )
+            .optional()?
+            .map(OrderRecord::try_from)
+            .transpose()
+    }
+
+    /// Returns the orders with the given status, oldest first.
+    pub fn get_orders(&self, status: OrderStatus) -> Result<Vec<OrderRecord>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM orders WHERE status = ?1 ORDER BY created_block, rowid")?;
+        let rows = stmt.query_map(params![status.to_string()], RawOrderRecord::from_row)?;
+
+        rows.map(|row| OrderRecord::try_from(row?)).collect()
+    }
+
+    /// Returns the fills of the order backed by `note_id`, oldest first.
+    pub fn get_fills(&self, note_id: NoteId) -> Result<Vec<FillRecord>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT note_id, fill_amount, successor_note_id, block_num FROM fills
+             WHERE note_id = ?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![note_id.to_hex()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, u32>(3)?,
+            ))
+        })?;
+
+        rows.map(|row| {
+            let (note_id, fill_amount, successor_note_id, block_num) = row?;
+            Ok(FillRecord {
+                note_id: parse_note_id(&note_id)?,
+                fill_amount: fill_amount as u64,
+                successor_note_id: successor_note_id
+                    .as_deref()
+                    .map(parse_note_id)
+                    .transpose()?,
+                block_num,
+            })
+        })
+        .collect()
+    }
+
+    /// Returns every status the order backed by `note_id` went through, with the block number of
+    /// the transition.
+    pub fn get_status_history(
+        &self,
+        note_id: NoteId,
+    ) -> Result<Vec<(OrderStatus, u32)>, StoreError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT status, block_num FROM status_transitions WHERE note_id = ?1 ORDER BY id",
+        )?;
+        let rows = stmt.query_map(params![note_id.to_hex()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+        })?;
+
+        rows.map(|row| {
+            let (status, block_num) = row?;
+            Ok((status.parse()?, block_num))
+        })
+        .collect()
+    }
+
+    /// Returns the block the note index was last updated at, `None` if it was never built.
+    pub fn get_note_index_block(&self) -> Result<Option<u32>, StoreError> {
+        Ok(self
+            .conn
+            .query_row("SELECT block_num FROM note_index", [], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// Replaces the note index with `notes`, the unspent notes of the client at `block_num`.
+    pub fn rebuild_note_index(
+        &mut self,
+        notes: &[(NoteId, NoteTag)],
+        block_num: u32,
+    ) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM note_tags", [])?;
+        update_note_index(&tx, notes, &[], block_num)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Adds the `notes` received by the client up to `block_num` to the note index, and removes the
+    /// `consumed` notes from it.
+    pub fn update_note_index(
+        &mut self,
+        notes: &[(NoteId, NoteTag)],
+        consumed: &[NoteId],
+        block_num: u32,
+    ) -> Result<(), StoreError> {
+        let tx = self.conn.transaction()?;
+        update_note_index(&tx, notes, consumed, block_num)?;
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Returns the ids of the indexed notes with the given tag, in the order they were indexed.
+    pub fn get_note_ids_by_tag(&self, tag: NoteTag) -> Result<Vec<NoteId>, StoreError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT note_id FROM note_tags WHERE tag = ?1 ORDER BY rowid")?;
+        let rows = stmt.query_map(params![tag.inner()], |row| row.get::<_, String>(0))?;
+
+        rows.map(|row| parse_note_id(&row?)).collect()
+    }
+}
+
+// Helpers
+// ================================================================================================
+
+fn insert_order(
+    tx: &Transaction,
+    order: Order,
+    parent_note_id: Option<NoteId>,
+    block_num: u32,
+) -> Result<(), StoreError> {
+    let note_id = order.id().ok_or(StoreError::ParsingError(
+        "Only orders backed by a note can be stored".to_string(),
+    ))?;
+    let tag = build_swap_tag(
+        NoteType::Public,
+        &order.source_asset(),
+        &order.target_asset(),
+    )
+    .map_err(|e| StoreError::ParsingError(e.to_string()))?;
+
+    let inserted = tx.execute(
+        "INSERT OR IGNORE INTO orders (note_id, tag, source_faucet_id, source_amount,
+         target_faucet_id, target_amount, parent_note_id, status, created_block, commit_block,
+         creator_id, good_after_block, expiry_block)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            note_id.to_hex(),
+            tag.inner(),
+            order.source_asset().faucet_id().to_string(),
+            order.source_asset().unwrap_fungible().amount() as i64,
+            order.target_asset().faucet_id().to_string(),
+            order.target_asset().unwrap_fungible().amount() as i64,
+            parent_note_id.map(|id| id.to_hex()),
+            OrderStatus::Open.to_string(),
+            block_num,
+            order.block_num(),
+            order.creator().map(|id| id.to_string()),
+            order.validity().good_after_block,
+            order.validity().expiry_block
+        ],
+    )?;
+
+    if inserted > 0 {
+        insert_status_transition(tx, note_id, OrderStatus::Open, block_num)?;
+    } else if let Some(commit_block) = order.block_num() {
+        tx.execute(
+            "UPDATE orders SET commit_block = ?2 WHERE note_id = ?1 AND commit_block IS NULL",
+            params![note_id.to_hex(), commit_block],
+        )?;
+    }
+
+    Ok(())
+}
+
+fn record_fill(
+    tx: &Transaction,
+    note_id: NoteId,
+    fill_amount: u64,
+    successor: Option<Order>,
+    block_num: u32,
+) -> Result<(), StoreError> {
+    let successor_note_id = match successor {
+        Some(successor) => {
+            insert_order(tx, successor, Some(note_id), block_num)?;
+            successor.id()
+        }
+        None => None,
+    };
+
+    tx.execute(
+        "INSERT INTO fills (note_id, fill_amount, successor_note_id, block_num)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            note_id.to_hex(),
+            fill_amount as i64,
+            successor_note_id.map(|id| id.to_hex()),
+            block_num
+        ],
+    )?;
+
+    let status = if successor_note_id.is_some() {
+        OrderStatus::PartiallyFilled
+    } else {
+        OrderStatus::Filled
+    };
+
+    update_status(tx, note_id, status, block_num)?;
+    remove_indexed_note(tx, note_id)
+}
+
+fn update_status(
+    tx: &Transaction,
+    note_id: NoteId,
+    status: OrderStatus,
+    block_num: u32,
+) -> Result<(), StoreError> {
+    let updated = tx.execute(
+        "UPDATE orders SET status = ?1 WHERE note_id = ?2",
+        params![status.to_string(), note_id.to_hex()],
+    )?;
+
+    if updated == 0 {
+        return Err(StoreError::OrderNotFound(note_id));
+    }
+
+    insert_status_transition(tx, note_id, status, block_num)
+}
+
+fn insert_status_transition(
+    tx: &Transaction,
+    note_id: NoteId,
+    status: OrderStatus,
+    block_num: u32,
+) -> Result<(), StoreError> {
+    tx.execute(
+        "INSERT INTO status_transitions (note_id, status, block_num) VALUES (?1, ?2, ?3)",
+        params![note_id.to_hex(), status.to_string(), block_num],
+    )?;
+
+    Ok(())
+}
+
+fn update_note_index(
+    tx: &Transaction,
+    notes: &[(NoteId, NoteTag)],
+    consumed: &[NoteId],
+    block_num: u32,
+) -> Result<(), StoreError> {
+    for (note_id, tag) in notes {
+        tx.execute(
+            "INSERT OR REPLACE INTO note_tags (note_id, tag) VALUES (?1, ?2)",
+            params![note_id.to_hex(), tag.inner()],
+        )?;
+    }
+    for note_id in consumed {
+        remove_indexed_note(tx, *note_id)?;
+    }
+
+    tx.execute(
+        "INSERT OR REPLACE INTO note_index (id, block_num) VALUES (0, ?1)",
+        params![block_num],
+    )?;
+
+    Ok(())
+}
+
+/// Removes a note from the note index, so that it is not matched again before the sync which sees
+/// it consumed.
+fn remove_indexed_note(tx: &Transaction, note_id: NoteId) -> Result<(), StoreError> {
+    tx.execute(
+        "DELETE FROM note_tags WHERE note_id = ?1",
+        params![note_id.to_hex()],
+    )?;
+
+    Ok(())
+}
+
+fn parse_note_id(hex: &str) -> Result<NoteId, StoreError> {
+    NoteId::try_from_hex(hex).map_err(|e| StoreError::ParsingError(e.to_string()))
+}
+
+fn parse_asset(faucet_id: &str, amount: i64) -> Result<Asset, StoreError> {
+    let faucet_id =
+        AccountId::from_hex(faucet_id).map_err(|e| StoreError::ParsingError(e.to_string()))?;
+    let asset = FungibleAsset::new(faucet_id, amount as u64)
+        .map_err(|e| StoreError::ParsingError(e.to_string()))?;
+
+    Ok(Asset::Fungible(asset))
+}
+
+/// Row of the `orders` table, before parsing.
+struct RawOrderRecord {
+    note_id: String,
+    tag: u32,
+    source_faucet_id: String,
+    source_amount: i64,
+    target_faucet_id: String,
+    target_amount: i64,
+    parent_note_id: Option<String>,
+    status: String,
+    created_block: u32,
+    commit_block: Option<u32>,
+    creator_id: Option<String>,
+    good_after_block: Option<u32>,
+    expiry_block: Option<u32>,
+}
+
+impl RawOrderRecord {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            note_id: row.get("note_id")?,
+            tag: row.get("tag")?,
+            source_faucet_id: row.get("source_faucet_id")?,
+            source_amount: row.get("source_amount")?,
+            target_faucet_id: row.get("target_faucet_id")?,
+            target_amount: row.get("target_amount")?,
+            parent_note_id: row.get("parent_note_id")?,
+            status: row.get("status")?,
+            created_block: row.get("created_block")?,
+            commit_block: row.get("commit_block")?,
+            creator_id: row.get("creator_id")?,
+            good_after_block: row.get("good_after_block")?,
+            expiry_block: row.get("expiry_block")?,
+        })
+    }
+}
+
+impl TryFrom<RawOrderRecord> for OrderRecord {
+    type Error = StoreError;
+
+    fn try_from(value: RawOrderRecord) -> Result<Self, Self::Error> {
+        let source_asset = parse_asset(&value.source_faucet_id, value.source_amount)?;
+        let target_asset = parse_asset(&value.target_faucet_id, value.target_amount)?;
+
+        let mut order = Order::new(
+            Some(parse_note_id(&value.note_id)?),
+            source_asset,
+            target_asset,
+        )
+        .with_validity(OrderValidity::new(
+            value.good_after_block,
+            value.expiry_block,
+        ));
+        if let Some(commit_block) = value.commit_block {
+            order = order.with_block_num(commit_block);
+        }
+        if let Some(creator_id) = value.creator_id {
+            order = order.with_creator(
+                AccountId::from_hex(&creator_id)
+                    .map_err(|e| StoreError::ParsingError(e.to_string()))?,
+            );
+        }
+
+        Ok(OrderRecord {
+            order,
+            tag: value.tag.into(),
+            parent_note_id: value
+                .parent_note_id
+                .as_deref()
+                .map(parse_note_id)
+                .transpose()?,
+            status: value.status.parse()?,
+            created_block: value.created_block,
+        })
+    }
+}
+
+// Tests
+// ================================================================================================
+
+#[cfg(test)]
+mod tests {
+    use miden_client::{
+        accounts::AccountId,
+        assets::{Asset, FungibleAsset},
+        notes::{NoteId, NoteTag},
+        Felt, ZERO,
+    };
+
+    use super::{OrderBookStore, OrderStatus};
+    use crate::order::{FillPlan, Order, OrderValidity};
+
+    fn build_order(note_id_hex: &str, source_amount: u64, target_amount: u64) -> Order {
+        let source_faucet_id = AccountId::from_hex("0x227bd163275aa1bf").unwrap();
+        let target_faucet_id = AccountId::from_hex("0x2540b08edc3b087d").unwrap();
+        let note_id = NoteId::try_from_hex(note_id_hex).unwrap();
+
+        Order::new(
+            Some(note_id),
+            Asset::Fungible(FungibleAsset::new(source_faucet_id, source_amount).unwrap()),
+            Asset::Fungible(FungibleAsset::new(target_faucet_id, target_amount).unwrap()),
+        )
+    }
+
+    #[test]
+    fn partial_fill_lineage_is_recorded() {
+        let mut store = OrderBookStore::open_in_memory().unwrap();
+
+        let order = build_order(
+            "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+            100,
+            50,
+        );
+        let successor = build_order(
+            "0x27c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+            60,
+            30,
+        );
+        let order_id = order.id().unwrap();
+        let successor_id = successor.id().unwrap();
+
+        store.insert_order(order, None, 1).unwrap();
+        // inserting the same order twice is a no-op
+        store.insert_order(order, None, 2).unwrap();
+        assert_eq!(store.get_orders(OrderStatus::Open).unwrap().len(), 1);
+
+        store.record_fill(order_id, 20, Some(successor), 3).unwrap();
+
+        let record = store.get_order(order_id).unwrap().unwrap();
+        assert_eq!(record.order, order);
+        assert_eq!(record.status, OrderStatus::PartiallyFilled);
+        assert_eq!(
+            store.get_status_history(order_id).unwrap(),
+            vec![(OrderStatus::Open, 1), (OrderStatus::PartiallyFilled, 3)]
+        );
+
+        let fills = store.get_fills(order_id).unwrap();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].fill_amount, 20);
+        assert_eq!(fills[0].successor_note_id, Some(successor_id));
+
+        let successor_record = store.get_order(successor_id).unwrap().unwrap();
+        assert_eq!(successor_record.parent_note_id, Some(order_id));
+        assert_eq!(successor_record.status, OrderStatus::Open);
+        assert_eq!(
+            store.get_orders(OrderStatus::Open).unwrap(),
+            vec![successor_record]
+        );
+    }
+
+    #[test]
+    fn commit_block_is_set_once_known() {
+        let mut store = OrderBookStore::open_in_memory().unwrap();
+
+        let order = build_order(
+            "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+            100,
+            50,
+        );
+        let order_id = order.id().unwrap();
+
+        store.insert_order(order, None, 1).unwrap();
+        assert_eq!(store.get_order(order_id).unwrap().unwrap().order, order);
+
+        store
+            .insert_order(order.with_block_num(2), None, 3)
+            .unwrap();
+        store
+            .insert_order(order.with_block_num(4), None, 5)
+            .unwrap();
+
+        let record = store.get_order(order_id).unwrap().unwrap();
+        assert_eq!(record.order, order.with_block_num(2));
+        assert_eq!(record.created_block, 1);
+    }
+
+    #[test]
+    fn cancelled_orders_are_no_longer_open() {
+        let mut store = OrderBookStore::open_in_memory().unwrap();
+
+        let order = build_order(
+            "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+            100,
+            50,
+        );
+        let order_id = order.id().unwrap();
+
+        // orders unknown to the store are inserted before being cancelled
+        store.record_cancels(&[order], 4).unwrap();
+
+        assert!(store.get_orders(OrderStatus::Open).unwrap().is_empty());
+        assert_eq!(
+            store.get_status_history(order_id).unwrap(),
+            vec![(OrderStatus::Open, 4), (OrderStatus::Cancelled, 4)]
+        );
+    }
+
+    #[test]
+    fn creator_and_validity_are_restored() {
+        let mut store = OrderBookStore::open_in_memory().unwrap();
+
+        let creator = AccountId::from_hex("0x9b6a0ea2ad6e5b3a").unwrap();
+        let order = build_order(
+            "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+            100,
+            50,
+        )
+        .with_creator(creator)
+        .with_validity(OrderValidity::new(Some(10), Some(20)));
+        let order_id = order.id().unwrap();
+
+        store.insert_order(order, None, 1).unwrap();
+
+        let record = store.get_order(order_id).unwrap().unwrap();
+        assert_eq!(record.order, order);
+        assert_eq!(record.order.creator(), Some(creator));
+    }
+
+    #[test]
+    fn note_index_is_updated_incrementally() {
+        let mut store = OrderBookStore::open_in_memory().unwrap();
+        assert_eq!(store.get_note_index_block().unwrap(), None);
+
+        let order = build_order(
+            "0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+            100,
+            50,
+        );
+        let order_id = order.id().unwrap();
+        let other_id = NoteId::try_from_hex(
+            "0x27c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129",
+        )
+        .unwrap();
+        let (tag, other_tag) = (NoteTag::from(1), NoteTag::from(2));
+
+        store
+            .rebuild_note_index(&[(order_id, tag), (other_id, other_tag)], 1)
+            .unwrap();
+        assert_eq!(store.get_note_index_block().unwrap(), Some(1));
+        assert_eq!(store.get_note_ids_by_tag(tag).unwrap(), vec![order_id]);
+
+        // consumed notes leave the index
+        store.update_note_index(&[], &[other_id], 2).unwrap();
+        assert_eq!(store.get_note_index_block().unwrap(), Some(2));
+        assert!(store.get_note_ids_by_tag(other_tag).unwrap().is_empty());
+
+        // filled notes leave the index before the sync which sees them consumed
+        store
+            .record_fills(
+                &FillPlan::new(&[order], &[[Felt::new(50), ZERO, ZERO, ZERO]]).unwrap(),
+                None,
+                3,
+            )
+            .unwrap();
+        assert!(store.get_note_ids_by_tag(tag).unwrap().is_empty());
+    }
+}
@@ -1,30 +1,40 @@
 use miden_client::{
     accounts::AccountId,
-    assets::{Asset, FungibleAsset},
-    config::{Endpoint, RpcConfig},
     crypto::{FeltRng, RpoRandomCoin},
-    notes::NoteTag,
+    notes::{Note, NoteId, NoteTag},
     rpc::TonicRpcClient,
     store::{
         sqlite_store::{config::SqliteStoreConfig, SqliteStore},
-        InputNoteRecord, NoteFilter, StoreAuthenticator,
+        InputNoteRecord, NoteFilter, StoreAuthenticator, TransactionFilter,
     },
-    transactions::NoteArgs,
-    Client, Felt,
+    sync::SyncSummary,
+    Client, ClientError, Felt,
 };
-use miden_tx::{LocalTransactionProver, ProvingOptions};
+use miden_tx::{LocalTransactionProver, ProvingOptions, TransactionProver};
+use miden_tx_prover::RemoteTransactionProver;
 use rand::Rng;
-use rusqlite::Connection;
 use std::sync::Arc;
 
-use crate::order::Order;
+use crate::{
+    book::Depth,
+    config::{ClientConfig, ProverConfig},
+    errors::{report, ConfigError, NoteIndexError, SwappNoteError},
+    note::{get_swapp_note_creator, is_swapp_note},
+    order::{FillPlan, Order},
+    store::OrderBookStore,
+};
 
 // Client Setup
 // ================================================================================================
 
-pub async fn setup_client() -> Client<impl FeltRng> {
-    let store_config = SqliteStoreConfig::default();
-    let store = SqliteStore::new(&store_config).await.unwrap();
+/// Creates a client from the given config.
+pub async fn setup_client(config: &ClientConfig) -> Result<Client<impl FeltRng>, ConfigError> {
+    let rpc_config = config.rpc_config()?;
+
+    let store_config = SqliteStoreConfig {
+        database_filepath: config.store_path.clone(),
+    };
+    let store = SqliteStore::new(&store_config).await?;
     let store = Arc::new(store);
 
     let mut rng = rand::thread_rng();
@@ -32,49 +42,192 @@ pub async fn setup_client() -> Client<impl FeltRng> {
 
     let rng = RpoRandomCoin::new(coin_seed.map(Felt::new));
     let authenticator = StoreAuthenticator::new_with_rng(store.clone(), rng);
-    let tx_prover = LocalTransactionProver::new(ProvingOptions::default());
-
-    let rpc_config = RpcConfig {
-        endpoint: Endpoint::new("http".to_string(), "localhost".to_string(), 57291),
-        timeout_ms: 10000,
+    let tx_prover: Arc<dyn TransactionProver> = match &config.prover {
+        ProverConfig::Local => Arc::new(LocalTransactionProver::new(ProvingOptions::default())),
+        ProverConfig::Remote { endpoint } => Arc::new(RemoteTransactionProver::new(endpoint)),
     };
 
-    let in_debug_mode = true;
-
-    Client::new(
+    Ok(Client::new(
         Box::new(TonicRpcClient::new(&rpc_config)),
         rng,
         store,
         Arc::new(authenticator),
-        Arc::new(tx_prover),
-        in_debug_mode,
-    )
+        tx_prover,
+        config.debug_mode,
+    ))
+}
+
+// Note Index
+// ================================================================================================
+
+/// Syncs the client and updates the note index of `store` with the notes received, committed and
+/// consumed by the sync.
+pub async fn sync_state(
+    client: &mut Client<impl FeltRng>,
+    store: &mut OrderBookStore,
+) -> Result<SyncSummary, NoteIndexError> {
+    // the notes of syncs which did not update the index are only found by rebuilding it
+    let up_to_date = is_note_index_up_to_date(client, store).await?;
+    let summary = client.sync_state().await?;
+    if !up_to_date {
+        rebuild_note_index(client, store).await?;
+        return Ok(summary);
+    }
+
+    let note_ids: Vec<NoteId> = summary
+        .received_notes
+        .iter()
+        .chain(summary.committed_notes.iter())
+        .copied()
+        .collect();
+    // committed notes include output notes of the client, which are not found as input notes
+    let note_tags = if note_ids.is_empty() {
+        Vec::new()
+    } else {
+        let notes = client.get_input_notes(NoteFilter::Unspent).await?;
+        select_note_tags(get_note_tags(&notes), &note_ids)
+    };
+    store.update_note_index(&note_tags, &summary.consumed_notes, summary.block_num)?;
+
+    Ok(summary)
+}
+
+/// Returns the unspent notes with the given tag, looked up in the note index of `store`.
+///
+/// The index is rebuilt from every unspent note of the client if it was never built, or if the
+/// client was synced without updating it.
+pub async fn get_notes_by_tag(
+    client: &Client<impl FeltRng>,
+    store: &mut OrderBookStore,
+    tag: NoteTag,
+) -> Result<Vec<InputNoteRecord>, NoteIndexError> {
+    if !is_note_index_up_to_date(client, store).await? {
+        rebuild_note_index(client, store).await?;
+    }
+
+    let note_ids = store.get_note_ids_by_tag(tag)?;
+    if note_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    Ok(client.get_input_notes(NoteFilter::List(note_ids)).await?)
+}
+
+async fn is_note_index_up_to_date(
+    client: &Client<impl FeltRng>,
+    store: &OrderBookStore,
+) -> Result<bool, NoteIndexError> {
+    let block_num = client.get_sync_height().await?;
+
+    Ok(store.get_note_index_block()? == Some(block_num))
 }
 
-pub async fn get_notes_by_tag(client: &Client<impl FeltRng>, tag: NoteTag) -> Vec<InputNoteRecord> {
-    let notes = client.get_input_notes(NoteFilter::Unspent).await.unwrap();
+async fn rebuild_note_index(
+    client: &Client<impl FeltRng>,
+    store: &mut OrderBookStore,
+) -> Result<(), NoteIndexError> {
+    let block_num = client.get_sync_height().await?;
+    let notes = client.get_input_notes(NoteFilter::Unspent).await?;
+    store.rebuild_note_index(&get_note_tags(&notes), block_num)?;
 
+    Ok(())
+}
+
+fn get_note_tags(notes: &[InputNoteRecord]) -> Vec<(NoteId, NoteTag)> {
     notes
+        .iter()
+        .filter_map(|note| note.metadata().map(|metadata| (note.id(), metadata.tag())))
+        .collect()
+}
+
+/// Keeps the tags of the notes in `note_ids`, the ids of the other notes are ignored.
+fn select_note_tags(
+    note_tags: Vec<(NoteId, NoteTag)>,
+    note_ids: &[NoteId],
+) -> Vec<(NoteId, NoteTag)> {
+    note_tags
         .into_iter()
-        .filter_map(|note| {
-            note.clone().metadata().and_then(|metadata| {
-                if metadata.tag() == tag {
-                    Some(note)
-                } else {
-                    None
-                }
-            })
-        })
+        .filter(|(note_id, _)| note_ids.contains(note_id))
         .collect()
 }
 
-pub fn get_assets_from_swap_note(note: &InputNoteRecord) -> (Asset, Asset) {
-    let source_asset =
-        Asset::Fungible(note.assets().iter().collect::<Vec<&Asset>>()[0].unwrap_fungible());
-    let target_faucet = AccountId::try_from(note.details().inputs().values()[7]).unwrap();
-    let target_amount = note.details().inputs().values()[4].as_int();
-    let target_asset = Asset::Fungible(FungibleAsset::new(target_faucet, target_amount).unwrap());
-    (source_asset, target_asset)
+/// Returns the orders of the unspent notes with the given tag, along with the notes which were
+/// rejected because they are not valid SWAPP notes.
+pub async fn get_orders_by_tag(
+    client: &Client<impl FeltRng>,
+    store: &mut OrderBookStore,
+    tag: NoteTag,
+) -> Result<(Vec<Order>, Vec<(NoteId, SwappNoteError)>), NoteIndexError> {
+    let mut orders = Vec::new();
+    let mut rejected = Vec::new();
+    for note in get_notes_by_tag(client, store, tag).await? {
+        let note_id = note.id();
+        match Order::try_from(note) {
+            Ok(order) => orders.push(order),
+            Err(err) => rejected.push((note_id, err)),
+        }
+    }
+
+    Ok((orders, rejected))
+}
+
+/// Returns the unspent SWAPP notes created by `creator`, only the ones with one of the given tags
+/// when `tags` is not empty, in which case they are looked up in the note index of `store`.
+pub async fn get_swapp_notes_by_creator(
+    client: &Client<impl FeltRng>,
+    store: &mut OrderBookStore,
+    creator: AccountId,
+    tags: &[NoteTag],
+) -> Result<Vec<Note>, NoteIndexError> {
+    let mut records = Vec::new();
+    if tags.is_empty() {
+        records = client.get_input_notes(NoteFilter::Unspent).await?;
+    }
+    for tag in tags {
+        records.extend(get_notes_by_tag(client, store, *tag).await?);
+    }
+
+    // notes without metadata can not be SWAPP notes of the creator
+    let notes = records
+        .into_iter()
+        .filter_map(|record| note_from_record(&record).ok())
+        .filter(|note| is_swapp_note(note) && get_swapp_note_creator(note) == Some(creator))
+        .collect();
+
+    Ok(notes)
+}
+
+/// Returns the note of an input note record, which must hold its metadata.
+pub fn note_from_record(record: &InputNoteRecord) -> Result<Note, SwappNoteError> {
+    let metadata = record.metadata().ok_or(SwappNoteError::MissingMetadata)?;
+
+    Ok(Note::new(
+        record.assets().clone(),
+        *metadata,
+        record.details().recipient().clone(),
+    ))
+}
+
+/// Returns the account which consumed a note, when the client knows the transaction which
+/// consumed it.
+///
+/// The node does not tell which account consumed a note, so only the consumers of the notes
+/// consumed by the accounts of the client are known.
+pub async fn get_note_consumer(
+    client: &Client<impl FeltRng>,
+    note_id: NoteId,
+) -> Result<Option<AccountId>, ClientError> {
+    let record = client.get_input_note(note_id).await?;
+    let Some(transaction_id) = record.consumer_transaction_id() else {
+        return Ok(None);
+    };
+
+    let transactions = client
+        .get_transactions(TransactionFilter::Ids(vec![*transaction_id]))
+        .await?;
+    Ok(transactions
+        .first()
+        .map(|transaction| transaction.account_id))
 }
 
 pub fn print_order_table(title: &str, orders: &[Order]) {
@@ -114,45 +267,107 @@ pub fn print_order_table(title: &str, orders: &[Order]) {
     }
 }
 
-pub fn print_balance_update(orders: &[Order], args: &[NoteArgs]) {
-    if orders.is_empty() {
-        println!("No orders to process. Your balance will not change.");
-        return;
+pub fn print_rejected_notes(rejected: &[(NoteId, SwappNoteError)]) {
+    for (note_id, err) in rejected {
+        println!(
+            "Skipping note {}, not a valid SWAPP note: {}",
+            note_id,
+            report(err)
+        );
     }
+}
 
-    let mut total_source_asset = 0u64;
-    let mut total_target_asset = 0u64;
-    let source_faucet_id = orders[0].target_asset().faucet_id();
-    let target_faucet_id = orders[0].source_asset().faucet_id();
+pub fn print_depth(title: &str, depth: &Depth) {
+    let mut table = Vec::new();
+    table.push("+------+--------------------+--------------------+----------+".to_string());
+    table.push("| Side | Price              | Quantity           | Orders   |".to_string());
+    table.push("+------+--------------------+--------------------+----------+".to_string());
+
+    for level in depth.asks.iter().rev() {
+        table.push(format!(
+            "| {:<4} | {:<18.6} | {:<18} | {:<8} |",
+            "Ask", level.price, level.quantity, level.num_orders
+        ));
+    }
 
-    for (i, order) in orders.into_iter().enumerate() {
-        total_source_asset += args[i][0].as_int();
-        total_target_asset += order.source_asset().unwrap_fungible().amount();
+    for level in depth.bids.iter() {
+        table.push(format!(
+            "| {:<4} | {:<18.6} | {:<18} | {:<8} |",
+            "Bid", level.price, level.quantity, level.num_orders
+        ));
     }
 
+    table.push("+------+--------------------+--------------------+----------+\n".to_string());
+
+    // Print title
+    println!("{}\n", title);
+
+    // Print table
+    for line in table {
+        println!("{}", line);
+    }
+}
+
+/// Prints the amounts exchanged with each note of `plan` and the resulting balance update of the
+/// account consuming them.
+pub fn print_balance_update(plan: &FillPlan) {
+    let Some(delta) = plan.balance_delta() else {
+        println!("No orders to process. Your balance will not change.");
+        return;
+    };
+
     println!("Balance Update Preview:");
     println!("------------------------");
+    for fill in plan.fills.iter() {
+        let note_id = fill
+            .order
+            .id()
+            .map_or_else(|| "N/A".to_string(), |id| id.to_string());
+        println!("Note {}:", note_id);
+        println!("  Pay: {}", fill.paid);
+        println!("  Receive: {}", fill.received);
+        if let Some(successor) = fill.successor {
+            println!(
+                "  Remaining in successor note: {} offered for {} requested",
+                successor.offered_asset.amount(),
+                successor.requested_asset.amount()
+            );
+        }
+    }
+    println!("------------------------");
     println!("Assets you will receive:");
-    println!("  Faucet ID: {}", target_faucet_id);
-    println!("  Amount: {}", total_target_asset);
+    println!("  Faucet ID: {}", delta.received_faucet_id);
+    println!("  Amount: {}", delta.received);
     println!("\nAssets you will spend:");
-    println!("  Faucet ID: {}", source_faucet_id);
-    println!("  Amount: {}", total_source_asset);
+    println!("  Faucet ID: {}", delta.spent_faucet_id);
+    println!("  Amount: {}", delta.spent);
     println!("------------------------");
 }
 
-pub fn clear_notes_tables(db_path: &str) {
-    // Open a connection to the SQLite database
-    let conn = Connection::open(db_path).unwrap();
+// Tests
+// ================================================================================================
 
-    // Execute the DELETE commands
-    conn.execute_batch(
-        "
-        DELETE FROM output_notes;
-        DELETE FROM input_notes;
-    ",
-    )
-    .unwrap();
+#[cfg(test)]
+mod tests {
+    use miden_client::notes::{NoteId, NoteTag};
 
-    println!("Both output_notes and input_notes tables have been cleared.");
+    use super::select_note_tags;
+
+    fn note_id(hex: &str) -> NoteId {
+        NoteId::try_from_hex(hex).unwrap()
+    }
+
+    #[test]
+    fn output_only_notes_are_not_indexed() {
+        let input = note_id("0x17c0bee79464320cc0d5d835cb9c2971b5c23fcea665c66d4f73c54fc7860129");
+        let unsynced =
+            note_id("0x2a3c5e7f9b1d3f5e7a9c1b3d5f7e9a1c3b5d7f9e1a3c5b7d9f1e3a5c7b9d1f3e");
+        // e.g. the P2ID payback note of a fill, committed but never an input note of the client
+        let output = note_id("0x0b1d3f5e7a9c1b3d5f7e9a1c3b5d7f9e1a3c5b7d9f1e3a5c7b9d1f3e5a7c9b1d");
+        let tag = NoteTag::from(42);
+
+        let note_tags = select_note_tags(vec![(input, tag), (unsynced, tag)], &[input, output]);
+
+        assert_eq!(note_tags, vec![(input, tag)]);
+    }
 }
@@ -5,17 +5,26 @@ use miden_client::{
     assets::AssetVault,
     crypto::RpoRandomCoin,
     notes::{
-        NoteAssets, NoteExecutionHint, NoteExecutionMode, NoteId, NoteMetadata, NoteTag, NoteType,
+        build_swap_tag, Note, NoteAssets, NoteExecutionHint, NoteExecutionMode, NoteId,
+        NoteMetadata, NoteTag, NoteType,
     },
     Felt, ZERO,
 };
-use miden_lib::transaction::TransactionKernel;
+use miden_lib::{notes::create_p2id_note, transaction::TransactionKernel};
 use miden_objects::{
     notes::NoteHeader,
     testing::account_code::DEFAULT_AUTH_SCRIPT,
     transaction::{TransactionArgs, TransactionScript},
 };
-use miden_order_book::note::create_swapp_note;
+use miden_order_book::{
+    errors::SwappNoteError,
+    note::{
+        create_expected_partial_swapp_note, create_expected_payback_note, create_swapp_note,
+        get_payback_serial_num, get_successor_serial_num, get_swapp_note_creator,
+    },
+    order::{Order, OrderValidity},
+    price::Price,
+};
 use miden_tx::testing::mock_chain::{Auth, MockChain};
 
 #[tokio::test]
@@ -41,6 +50,7 @@ async fn test_swapp_script_full_swap() {
         requested_asset,
         NoteType::Public,
         Felt::new(27),
+        OrderValidity::default(),
         &mut RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
     )
     .unwrap();
@@ -115,6 +125,13 @@ async fn test_swapp_script_full_swap() {
         NoteHeader::from(output_note),
         NoteHeader::new(note_id, note_metadata)
     );
+
+    // Check that the payback note is predicted from the SWAPP note
+    let payback_note = create_expected_payback_note(target_account.id(), &note, 10).unwrap();
+    assert_eq!(
+        NoteHeader::from(output_note),
+        NoteHeader::from(&payback_note)
+    );
 }
 
 #[tokio::test]
@@ -138,6 +155,12 @@ async fn test_swapp_script_partial_swap() {
     // assets for target account vault after transaction
     let received_offered_asset = faucet_1.mint(350_000_000);
 
+    // the amount received by the target account is predicted exactly
+    assert_eq!(
+        Price::new(1_000_000_000, 20_000_000_000_000).partial_exchange(7_000_000_000_000),
+        Some(350_000_000)
+    );
+
     // create sender and target account
     let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![offered_asset]);
     let target_account = chain.add_existing_wallet(Auth::BasicAuth, vec![requested_asset]);
@@ -148,6 +171,7 @@ async fn test_swapp_script_partial_swap() {
         requested_asset,
         NoteType::Public,
         Felt::new(27),
+        OrderValidity::default(),
         &mut RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
     )
     .unwrap();
@@ -230,6 +254,14 @@ async fn test_swapp_script_partial_swap() {
         NoteHeader::new(note_id, note_metadata)
     );
 
+    // Check that the payback note is predicted from the SWAPP note
+    let payback_note =
+        create_expected_payback_note(target_account.id(), &note, 7_000_000_000_000).unwrap();
+    assert_eq!(
+        NoteHeader::from(p2id_output_note),
+        NoteHeader::from(&payback_note)
+    );
+
     // SWAPP note
     let recipient = executed_transaction
         .output_notes()
@@ -253,4 +285,535 @@ async fn test_swapp_script_partial_swap() {
         NoteHeader::from(swapp_output_note),
         NoteHeader::new(note_id, note_metadata)
     );
+
+    // Check that the SWAPP note, and its serial number, are predicted by the taker
+    let expected_swapp_note =
+        create_expected_partial_swapp_note(target_account.id(), note.clone(), 7_000_000_000_000)
+            .unwrap();
+    assert_eq!(swapp_output_note.id(), expected_swapp_note.id());
+}
+
+#[tokio::test]
+async fn test_swapp_script_reclaim() {
+    // Setup
+    // --------------------------------------------------------------------------------------------
+    let mut chain = MockChain::new();
+
+    // create assets
+    let faucet_1 = chain.add_existing_faucet(Auth::NoAuth, "BTC", 10);
+    let faucet_2 = chain.add_existing_faucet(Auth::NoAuth, "ETH", 10);
+
+    let offered_asset = faucet_1.mint(10);
+    let requested_asset = faucet_2.mint(10);
+
+    // create sender account, its offered asset is held by the note
+    let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+
+    let note = create_swapp_note(
+        sender_account.id(),
+        offered_asset,
+        requested_asset,
+        NoteType::Public,
+        Felt::new(27),
+        OrderValidity::default(),
+        &mut RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+    )
+    .unwrap();
+    assert_eq!(get_swapp_note_creator(&note), Some(sender_account.id()));
+
+    // add note to chain
+    chain.add_note(note.clone());
+    chain.seal_block(None);
+
+    // EXECUTE TX
+    // --------------------------------------------------------------------------------------------
+    let transaction_script =
+        TransactionScript::compile(DEFAULT_AUTH_SCRIPT, vec![], TransactionKernel::assembler())
+            .unwrap();
+
+    // the creator consumes its own note without note args
+    let tx_context = chain
+        .build_tx_context(sender_account.id())
+        .tx_script(transaction_script)
+        .build();
+
+    let executed_transaction = tx_context.execute().await.unwrap();
+
+    // sender account vault delta
+    let sender_account_after: Account = Account::from_parts(
+        sender_account.id(),
+        AssetVault::new(&[offered_asset]).unwrap(),
+        sender_account.storage().clone(),
+        sender_account.code().clone(),
+        Felt::new(2),
+    );
+
+    // Check that the sender account got the offered asset back
+    assert_eq!(
+        executed_transaction.final_account().hash(),
+        sender_account_after.hash()
+    );
+
+    // Check that no payback or SWAPP note has been created
+    assert_eq!(executed_transaction.output_notes().num_notes(), 0);
+}
+
+#[tokio::test]
+async fn test_swapp_script_expired() {
+    // Setup
+    // --------------------------------------------------------------------------------------------
+    let mut chain = MockChain::new();
+
+    // create assets
+    let faucet_1 = chain.add_existing_faucet(Auth::NoAuth, "BTC", 10);
+    let faucet_2 = chain.add_existing_faucet(Auth::NoAuth, "ETH", 10);
+
+    let offered_asset = faucet_1.mint(10);
+    let requested_asset = faucet_2.mint(10);
+
+    // create sender and target account
+    let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let target_account = chain.add_existing_wallet(Auth::BasicAuth, vec![requested_asset]);
+
+    // the note can only be filled up to block 1
+    let validity = OrderValidity::new(None, Some(1));
+    let note = create_swapp_note(
+        sender_account.id(),
+        offered_asset,
+        requested_asset,
+        NoteType::Public,
+        Felt::new(27),
+        validity,
+        &mut RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+    )
+    .unwrap();
+    assert_eq!(Order::try_from(&note).unwrap().validity(), validity);
+
+    // add note to chain, and move the chain past the expiry block
+    chain.add_note(note.clone());
+    chain.seal_block(None);
+    chain.seal_block(None);
+    chain.seal_block(None);
+
+    // EXECUTE TX
+    // --------------------------------------------------------------------------------------------
+    let transaction_script =
+        TransactionScript::compile(DEFAULT_AUTH_SCRIPT, vec![], TransactionKernel::assembler())
+            .unwrap();
+
+    // the note can no longer be filled
+    let mut tx_context = chain
+        .build_tx_context(target_account.id())
+        .tx_script(transaction_script.clone())
+        .build();
+
+    let note_args = [Felt::new(10), Felt::new(0), Felt::new(0), Felt::new(0)];
+
+    let note_args_map = BTreeMap::from([(note.id(), note_args)]);
+
+    let tx_args = TransactionArgs::new(
+        Some(transaction_script.clone()),
+        Some(note_args_map),
+        tx_context.tx_args().advice_inputs().clone().map,
+    );
+
+    tx_context.set_tx_args(tx_args);
+
+    assert!(tx_context.execute().await.is_err());
+
+    // but the creator can still reclaim it
+    let tx_context = chain
+        .build_tx_context(sender_account.id())
+        .tx_script(transaction_script)
+        .build();
+
+    let executed_transaction = tx_context.execute().await.unwrap();
+
+    // sender account vault delta
+    let sender_account_after: Account = Account::from_parts(
+        sender_account.id(),
+        AssetVault::new(&[offered_asset]).unwrap(),
+        sender_account.storage().clone(),
+        sender_account.code().clone(),
+        Felt::new(2),
+    );
+
+    // Check that the sender account got the offered asset back
+    assert_eq!(
+        executed_transaction.final_account().hash(),
+        sender_account_after.hash()
+    );
+}
+
+#[tokio::test]
+async fn test_swapp_script_good_after() {
+    // Setup
+    // --------------------------------------------------------------------------------------------
+    let mut chain = MockChain::new();
+
+    // create assets
+    let faucet_1 = chain.add_existing_faucet(Auth::NoAuth, "BTC", 10);
+    let faucet_2 = chain.add_existing_faucet(Auth::NoAuth, "ETH", 10);
+
+    let offered_asset = faucet_1.mint(10);
+    let requested_asset = faucet_2.mint(10);
+
+    // create sender and target account
+    let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let target_account = chain.add_existing_wallet(Auth::BasicAuth, vec![requested_asset]);
+
+    // the note can only be filled from block 2
+    let validity = OrderValidity::new(Some(2), None);
+    let note = create_swapp_note(
+        sender_account.id(),
+        offered_asset,
+        requested_asset,
+        NoteType::Public,
+        Felt::new(27),
+        validity,
+        &mut RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+    )
+    .unwrap();
+    assert_eq!(Order::try_from(&note).unwrap().validity(), validity);
+
+    // add note to chain
+    chain.add_note(note.clone());
+    chain.seal_block(None);
+
+    // EXECUTE TX
+    // --------------------------------------------------------------------------------------------
+    let transaction_script =
+        TransactionScript::compile(DEFAULT_AUTH_SCRIPT, vec![], TransactionKernel::assembler())
+            .unwrap();
+
+    let note_args = [Felt::new(10), Felt::new(0), Felt::new(0), Felt::new(0)];
+
+    // the note can not be filled before its good after block
+    let mut tx_context = chain
+        .build_tx_context(target_account.id())
+        .tx_script(transaction_script.clone())
+        .build();
+    assert_eq!(tx_context.tx_inputs().block_header().block_num(), 1);
+
+    let tx_args = TransactionArgs::new(
+        Some(transaction_script.clone()),
+        Some(BTreeMap::from([(note.id(), note_args)])),
+        tx_context.tx_args().advice_inputs().clone().map,
+    );
+
+    tx_context.set_tx_args(tx_args);
+
+    assert!(tx_context.execute().await.is_err());
+
+    // but it can be filled at its good after block
+    chain.seal_block(None);
+
+    let mut tx_context = chain
+        .build_tx_context(target_account.id())
+        .tx_script(transaction_script.clone())
+        .build();
+    assert_eq!(tx_context.tx_inputs().block_header().block_num(), 2);
+
+    let tx_args = TransactionArgs::new(
+        Some(transaction_script),
+        Some(BTreeMap::from([(note.id(), note_args)])),
+        tx_context.tx_args().advice_inputs().clone().map,
+    );
+
+    tx_context.set_tx_args(tx_args);
+
+    let executed_transaction = tx_context.execute().await.unwrap();
+
+    // target account vault delta
+    let target_account_after: Account = Account::from_parts(
+        target_account.id(),
+        AssetVault::new(&[offered_asset]).unwrap(),
+        target_account.storage().clone(),
+        target_account.code().clone(),
+        Felt::new(2),
+    );
+
+    // Check that the target account has received the asset from the note
+    assert_eq!(
+        executed_transaction.final_account().hash(),
+        target_account_after.hash()
+    );
+
+    // Check that only the payback note has been created
+    assert_eq!(executed_transaction.output_notes().num_notes(), 1);
+}
+
+#[tokio::test]
+async fn test_swapp_payback_notes_are_consumed_by_the_maker() {
+    // Setup
+    // --------------------------------------------------------------------------------------------
+    let mut chain = MockChain::new();
+
+    // create assets
+    let faucet_1 = chain.add_existing_faucet(Auth::NoAuth, "BTC", 100);
+    let faucet_2 = chain.add_existing_faucet(Auth::NoAuth, "ETH", 100);
+
+    let offered_asset = faucet_1.mint(10);
+    let requested_asset = faucet_2.mint(10);
+
+    // create sender and target account
+    let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+    let target_account = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+
+    let note = create_swapp_note(
+        sender_account.id(),
+        offered_asset,
+        requested_asset,
+        NoteType::Public,
+        Felt::new(27),
+        OrderValidity::default(),
+        &mut RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+    )
+    .unwrap();
+
+    // payback notes of a full and of a partial fill of the note, as checked against the notes
+    // created by the SWAPP script in the tests above
+    let full_payback_note = create_expected_payback_note(target_account.id(), &note, 10).unwrap();
+    let partial_payback_note = create_expected_payback_note(target_account.id(), &note, 7).unwrap();
+    assert_ne!(full_payback_note.id(), partial_payback_note.id());
+
+    // add payback notes to chain
+    chain.add_note(full_payback_note);
+    chain.add_note(partial_payback_note);
+    chain.seal_block(None);
+
+    // EXECUTE TX
+    // --------------------------------------------------------------------------------------------
+    let transaction_script =
+        TransactionScript::compile(DEFAULT_AUTH_SCRIPT, vec![], TransactionKernel::assembler())
+            .unwrap();
+
+    // the creator consumes its payback notes like any P2ID note
+    let tx_context = chain
+        .build_tx_context(sender_account.id())
+        .tx_script(transaction_script)
+        .build();
+
+    let executed_transaction = tx_context.execute().await.unwrap();
+
+    // sender account vault delta
+    let sender_account_after: Account = Account::from_parts(
+        sender_account.id(),
+        AssetVault::new(&[faucet_2.mint(17)]).unwrap(),
+        sender_account.storage().clone(),
+        sender_account.code().clone(),
+        Felt::new(2),
+    );
+
+    // Check that the sender account received the requested asset of both fills
+    assert_eq!(
+        executed_transaction.final_account().hash(),
+        sender_account_after.hash()
+    );
+}
+
+#[tokio::test]
+async fn test_swapp_equal_fills_pay_back_with_distinct_notes() {
+    // Setup
+    // --------------------------------------------------------------------------------------------
+    let mut chain = MockChain::new();
+
+    // create assets
+    let faucet_1 = chain.add_existing_faucet(Auth::NoAuth, "BTC", 100);
+    let faucet_2 = chain.add_existing_faucet(Auth::NoAuth, "ETH", 100);
+
+    let offered_asset = faucet_1.mint(10);
+    let requested_asset = faucet_2.mint(10);
+
+    // create sender and target account
+    let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![offered_asset]);
+    let target_account = chain.add_existing_wallet(Auth::BasicAuth, vec![requested_asset]);
+
+    let note = create_swapp_note(
+        sender_account.id(),
+        offered_asset,
+        requested_asset,
+        NoteType::Public,
+        Felt::new(27),
+        OrderValidity::default(),
+        &mut RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]),
+    )
+    .unwrap();
+    let successor =
+        create_expected_partial_swapp_note(target_account.id(), note.clone(), 3).unwrap();
+
+    // the order is filled twice with 3, the second time through the successor of its note
+    chain.add_note(note.clone());
+    chain.add_note(successor.clone());
+    chain.seal_block(None);
+
+    // EXECUTE TX
+    // --------------------------------------------------------------------------------------------
+    let transaction_script =
+        TransactionScript::compile(DEFAULT_AUTH_SCRIPT, vec![], TransactionKernel::assembler())
+            .unwrap();
+
+    let mut payback_notes = Vec::new();
+    for swapp_note in [&note, &successor] {
+        let mut tx_context = chain
+            .build_tx_context(target_account.id())
+            .tx_script(transaction_script.clone())
+            .build();
+
+        let note_args = [Felt::new(3), Felt::new(0), Felt::new(0), Felt::new(0)];
+        let note_args_map = BTreeMap::from([(swapp_note.id(), note_args)]);
+        let tx_args = TransactionArgs::new(
+            Some(transaction_script.clone()),
+            Some(note_args_map),
+            tx_context.tx_args().advice_inputs().clone().map,
+        );
+        tx_context.set_tx_args(tx_args);
+
+        let executed_transaction = tx_context.execute().await.unwrap();
+        let payback_output_note = executed_transaction.output_notes().get_note(0);
+
+        // Check that the payback note is predicted from the filled SWAPP note
+        let payback_note =
+            create_expected_payback_note(target_account.id(), swapp_note, 3).unwrap();
+        assert_eq!(
+            NoteHeader::from(payback_output_note),
+            NoteHeader::from(&payback_note)
+        );
+        assert_eq!(
+            payback_note.serial_num(),
+            get_payback_serial_num(swapp_note.serial_num())
+        );
+        payback_notes.push(payback_note);
+    }
+
+    // Check that the payback notes of both fills are distinct notes
+    assert_ne!(payback_notes[0].id(), payback_notes[1].id());
+    assert_ne!(payback_notes[0].nullifier(), payback_notes[1].nullifier());
+}
+
+#[test]
+fn test_identical_swapp_notes_are_distinct() {
+    let mut chain = MockChain::new();
+
+    let faucet_1 = chain.add_existing_faucet(Auth::NoAuth, "BTC", 10);
+    let faucet_2 = chain.add_existing_faucet(Auth::NoAuth, "ETH", 10);
+    let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+
+    // two identical orders of the same account
+    let mut rng = RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]);
+    let mut create_note = || {
+        create_swapp_note(
+            sender_account.id(),
+            faucet_1.mint(10),
+            faucet_2.mint(10),
+            NoteType::Public,
+            Felt::new(27),
+            OrderValidity::default(),
+            &mut rng,
+        )
+        .unwrap()
+    };
+    let note_1 = create_note();
+    let note_2 = create_note();
+
+    assert_ne!(note_1.serial_num(), note_2.serial_num());
+    assert_ne!(note_1.id(), note_2.id());
+    assert_ne!(note_1.nullifier(), note_2.nullifier());
+
+    // the SWAPP notes of successive partial fills are distinct from the filled note too
+    let successor_1 =
+        create_expected_partial_swapp_note(sender_account.id(), note_1.clone(), 3).unwrap();
+    let successor_2 =
+        create_expected_partial_swapp_note(sender_account.id(), successor_1.clone(), 3).unwrap();
+    assert_eq!(
+        successor_1.serial_num(),
+        get_successor_serial_num(note_1.serial_num())
+    );
+    for (a, b) in [
+        (&note_1, &successor_1),
+        (&successor_1, &successor_2),
+        (&note_1, &successor_2),
+    ] {
+        assert_ne!(a.id(), b.id());
+        assert_ne!(a.nullifier(), b.nullifier());
+    }
+}
+
+#[test]
+fn test_only_swapp_notes_are_read_as_orders() {
+    let mut chain = MockChain::new();
+
+    let faucet_1 = chain.add_existing_faucet(Auth::NoAuth, "BTC", 10);
+    let faucet_2 = chain.add_existing_faucet(Auth::NoAuth, "ETH", 10);
+    let sender_account = chain.add_existing_wallet(Auth::BasicAuth, vec![]);
+
+    let offered_asset = faucet_1.mint(10);
+    let requested_asset = faucet_2.mint(20);
+
+    let mut rng = RpoRandomCoin::new([Felt::new(1), Felt::new(2), Felt::new(3), Felt::new(4)]);
+    let swapp_note = create_swapp_note(
+        sender_account.id(),
+        offered_asset,
+        requested_asset,
+        NoteType::Public,
+        ZERO,
+        OrderValidity::default(),
+        &mut rng,
+    )
+    .unwrap();
+
+    let order = Order::try_from(&swapp_note).unwrap();
+    assert_eq!(order.id(), Some(swapp_note.id()));
+    assert_eq!(order.source_asset(), offered_asset);
+    assert_eq!(order.target_asset(), requested_asset);
+    assert_eq!(order.creator(), Some(sender_account.id()));
+
+    // same SWAPP recipient, tagged for the opposite side of the pair
+    let other_tag = build_swap_tag(NoteType::Public, &requested_asset, &offered_asset).unwrap();
+    let metadata = NoteMetadata::new(
+        sender_account.id(),
+        NoteType::Public,
+        other_tag,
+        NoteExecutionHint::always(),
+        ZERO,
+    )
+    .unwrap();
+    let mistagged_note = Note::new(
+        swapp_note.assets().clone(),
+        metadata,
+        swapp_note.recipient().clone(),
+    );
+    assert_eq!(
+        Order::try_from(&mistagged_note),
+        Err(SwappNoteError::TagMismatch(other_tag))
+    );
+
+    // same SWAPP recipient, holding both assets
+    let assets = NoteAssets::new(vec![offered_asset, requested_asset]).unwrap();
+    let bundled_note = Note::new(
+        assets,
+        *swapp_note.metadata(),
+        swapp_note.recipient().clone(),
+    );
+    assert_eq!(
+        Order::try_from(&bundled_note),
+        Err(SwappNoteError::WrongNumberOfAssets(2))
+    );
+
+    // another script
+    let p2id_note = create_p2id_note(
+        sender_account.id(),
+        sender_account.id(),
+        vec![offered_asset],
+        NoteType::Public,
+        ZERO,
+        &mut rng,
+    )
+    .unwrap();
+    assert_eq!(
+        Order::try_from(&p2id_note),
+        Err(SwappNoteError::NotSwappScript(
+            p2id_note.recipient().script().hash()
+        ))
+    );
 }